@@ -0,0 +1,121 @@
+//! Drives a headless egui context through ten seconds of playback and
+//! measures what each way of getting frames on screen hands to the
+//! renderer: cropping and uploading every repaint (the old path), keeping
+//! one texture per frame shown (what upscaled themes use), and uploading
+//! the whole sheet once and picking frames by UV rectangle.
+//!
+//! The times cover egui's side of the work, building the texture deltas
+//! and painting; the GPU copy itself is left to the backend and isn't
+//! counted. Run with `cargo bench --bench frame_upload`.
+
+use eframe::egui;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+#[path = "../src/sheet.rs"]
+mod sheet;
+
+use sheet::{extract_frame, load_sprite_sheet, FRAMES_PER_ROW, FRAME_H, FRAME_W};
+
+// Ten seconds at the 60 Hz repaint rate used while a clip plays.
+const REPAINTS: u32 = 600;
+// A clip frame lasts about four repaints at that rate.
+const REPAINTS_PER_FRAME: u32 = 4;
+
+/// Plays the first row of the sheet over and over.
+fn frame_at(repaint: u32) -> (u32, u32) {
+    ((repaint / REPAINTS_PER_FRAME) % FRAMES_PER_ROW, 0)
+}
+
+fn color_image(image: &image::RgbaImage) -> egui::ColorImage {
+    let size = [image.width() as usize, image.height() as usize];
+    egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw())
+}
+
+#[derive(Default)]
+struct Totals {
+    time: Duration,
+    uploads: usize,
+    bytes: usize,
+}
+
+/// Runs `REPAINTS` passes of `paint`, which returns the texture and UV
+/// rectangle to draw for a frame, and adds up the texture deltas. One-time
+/// uploads are spread over every repaint.
+fn play(
+    mut paint: impl FnMut(&egui::Context, (u32, u32)) -> (egui::TextureId, egui::Rect),
+) -> Totals {
+    let ctx = egui::Context::default();
+    // Gets the font atlas uploaded before anything is counted.
+    let _ = ctx.run(egui::RawInput::default(), |_| {});
+    let mut totals = Totals::default();
+    for repaint in 0..REPAINTS {
+        let start = Instant::now();
+        let output = ctx.run(egui::RawInput::default(), |ctx| {
+            let (texture, uv) = paint(ctx, frame_at(repaint));
+            egui::CentralPanel::default().show(ctx, |ui| {
+                let rect = egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(FRAME_W as f32, FRAME_H as f32),
+                );
+                ui.painter().image(texture, rect, uv, egui::Color32::WHITE);
+            });
+        });
+        black_box(ctx.tessellate(output.shapes, output.pixels_per_point));
+        totals.time += start.elapsed();
+
+        for (_, delta) in &output.textures_delta.set {
+            totals.uploads += 1;
+            totals.bytes += delta.image.width() * delta.image.height() * 4;
+        }
+    }
+    totals
+}
+
+fn report(name: &str, totals: &Totals) {
+    println!(
+        "  {name:<30} {:>10.1?} per repaint, {:>4} uploads, {:>6} KiB",
+        totals.time / REPAINTS,
+        totals.uploads,
+        totals.bytes / 1024
+    );
+}
+
+fn main() {
+    let sheet = load_sprite_sheet();
+    let full_uv = egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0));
+
+    // Dropping last repaint's handle frees it, as the old path did.
+    let mut current = None;
+    let cropped = play(|ctx, (x, y)| {
+        let image = color_image(&extract_frame(&sheet, x, y));
+        let handle = current.insert(ctx.load_texture("frame", image, Default::default()));
+        (handle.id(), full_uv)
+    });
+
+    let mut cache = HashMap::new();
+    let per_frame = play(|ctx, (x, y)| {
+        let handle = cache.entry((x, y)).or_insert_with(|| {
+            let image = color_image(&extract_frame(&sheet, x, y));
+            ctx.load_texture(format!("frame-{x}-{y}"), image, Default::default())
+        });
+        (handle.id(), full_uv)
+    });
+
+    let mut atlas = None;
+    let uv_rects = play(|ctx, (x, y)| {
+        let atlas = atlas.get_or_insert_with(|| {
+            ctx.load_texture("sheet", color_image(&sheet), Default::default())
+        });
+        let [width, height] = atlas.size();
+        let uv_size = egui::vec2(FRAME_W as f32 / width as f32, FRAME_H as f32 / height as f32);
+        let uv_min = egui::pos2(x as f32 * uv_size.x, y as f32 * uv_size.y);
+        (atlas.id(), egui::Rect::from_min_size(uv_min, uv_size))
+    });
+
+    println!("{REPAINTS} repaints (10 s of playback at 60 Hz)");
+    report("crop + upload every repaint:", &cropped);
+    report("one texture per frame:", &per_frame);
+    report("sheet atlas + UV rects:", &uv_rects);
+}
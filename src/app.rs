@@ -0,0 +1,1521 @@
+//! The default eframe window, with the full context menu, zoom, themes,
+//! click-through and ghost mode.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::balloon::{self, Balloon, Choices, Outcome, Placement, Side};
+use crate::control::{self, ControlServer};
+use crate::cursor;
+use crate::effects::{self, Theme};
+use crate::ghost::{self, GhostMode};
+use crate::history::{self, History};
+use crate::manifest::{ClipManifest, Interaction, LookDirection};
+use crate::monitor;
+use crate::perf::PerfStats;
+use crate::personality::Personality;
+use crate::tips::{Tip, Tips};
+use crate::upscale::{self, Upscale};
+use crate::{
+    extract_frame, load_sprite_sheet, Animation, DRAG_SETTLE_MS, FRAME_DURATION_MS, FRAME_H,
+    FRAME_W,
+};
+
+/// Names the window and the directory settings and the history are kept in.
+const APP_NAME: &str = "Tiny Clippy";
+const LOOK_LINGER_MS: u64 = 300;
+const LOOK_DEAD_ZONE: f32 = 12.0;
+/// Distance in points from Clippy's middle beyond which he stops following
+/// the cursor. Kept inside `CURSOR_NEAR` so looking never waits on the slow
+/// poll.
+const LOOK_RADIUS: f32 = 300.0;
+const HOVER_REACT_MS: u64 = 2000;
+const DEBUG_REFRESH_MS: u64 = 100;
+const ZOOM_KEY: &str = "zoom";
+const CLICK_THROUGH_KEY: &str = "click_through";
+const THEME_KEY: &str = "theme";
+const UPSCALE_KEY: &str = "upscale";
+const GHOST_KEY: &str = "ghost";
+const INPUT_HANDLER_KEY: &str = "input_handler";
+const TIP_INTERVAL_KEY: &str = "tip_interval";
+const TIP_TOPIC_KEY: &str = "tip_topic";
+const PERSONALITY_KEY: &str = "personality";
+/// How often the cursor is checked for Clippy to look at or fade from
+/// while it is near but outside the window and sends no events.
+const CURSOR_POLL_MS: u64 = 100;
+/// The same once the cursor is further away than `CURSOR_NEAR`, where
+/// nothing is about to change.
+const CURSOR_IDLE_POLL_MS: u64 = 1000;
+/// Distance in points from Clippy within which the cursor is polled fast.
+const CURSOR_NEAR: f32 = 400.0;
+const CLICK_THROUGH_POLL_MS: u64 = 50;
+/// How long to wait for the window to get where it was moved before
+/// placing the balloon from wherever it ended up.
+const MOVE_SETTLE_MS: u64 = 250;
+const HIT_ALPHA: u8 = 32;
+
+const SAY_USAGE: &str =
+    "usage: tiny-clippy say [--timeout SECS] [--rate CPS] [--source NAME] TEXT";
+const ASK_USAGE: &str = "usage: tiny-clippy ask [--timeout SECS] [--rate CPS] [--default CHOICE] \
+[--list] [--hook CMD] [--source NAME] QUESTION CHOICE CHOICE...";
+const INPUT_USAGE: &str = "usage: tiny-clippy input [--hint TEXT] [--timeout SECS] [--rate CPS] \
+[--hook CMD] [--source NAME] [PROMPT]";
+const TIP_USAGE: &str = "usage: tiny-clippy tip [--tag TAG] [--rate CPS]";
+const INPUT_PROMPT: &str = "What would you like to do?";
+const INPUT_HINT: &str = "Type your question here";
+const TIP_THANKS: &str = "Thanks";
+const TIP_FEWER: &str = "Fewer like this";
+const TIP_NEVER: &str = "Don't show again";
+
+pub fn run() -> Result<(), String> {
+    launch(None)
+}
+
+/// `tiny-clippy say`: shows Clippy with a speech balloon and exits once the
+/// balloon is clicked away or times out. Without `--timeout` it stays up
+/// for about as long as the text takes to read; `--timeout 0` waits for a
+/// click. The text is typed out at `--rate` characters per second, or all
+/// at once with `--rate 0`. `--source` names the script in the history.
+pub fn say(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut timeout, mut rate) = (None, balloon::DEFAULT_REVEAL_RATE);
+    let mut source = "say".to_string();
+    let mut words = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => timeout = Some(parse_timeout(args.next())?),
+            "--rate" => rate = parse_rate(args.next())?,
+            "--source" => source = args.next().ok_or("--source needs a value")?,
+            "--help" | "-h" => return Err(SAY_USAGE.to_string()),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option `{flag}`\n{SAY_USAGE}"));
+            }
+            word => words.push(word.to_string()),
+        }
+    }
+    if words.is_empty() {
+        return Err(format!("nothing to say\n{SAY_USAGE}"));
+    }
+
+    launch(Some(say_balloon(words.join(" "), timeout, rate).with_source(source)))
+}
+
+/// A balloon that just says `text`. Without `timeout` it stays up for about
+/// as long as the text takes to read; a zero timeout waits for a click.
+fn say_balloon(text: String, timeout: Option<Duration>, rate: f32) -> Balloon {
+    let timeout = match timeout {
+        None => Some(Balloon::reading_time(&text)),
+        Some(timeout) if timeout.is_zero() => None,
+        Some(timeout) => Some(timeout),
+    };
+    Balloon::new(text, timeout).with_reveal(rate)
+}
+
+/// `tiny-clippy ask`: puts a question to the user and prints the choice,
+/// or the default choice if it times out.
+pub fn ask(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut timeout, mut default, mut list, mut hook) = (None, None, false, None);
+    let mut rate = balloon::DEFAULT_REVEAL_RATE;
+    let mut source = "ask".to_string();
+    let mut positional = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+        match arg.as_str() {
+            "--timeout" => timeout = Some(parse_timeout(args.next())?),
+            "--rate" => rate = parse_rate(args.next())?,
+            "--default" => default = Some(value("--default")?),
+            "--list" => list = true,
+            "--hook" => hook = Some(value("--hook")?),
+            "--source" => source = value("--source")?,
+            "--help" | "-h" => return Err(ASK_USAGE.to_string()),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option `{flag}`\n{ASK_USAGE}"));
+            }
+            word => positional.push(word.to_string()),
+        }
+    }
+    if positional.is_empty() {
+        return Err(format!("nothing to ask\n{ASK_USAGE}"));
+    }
+
+    let question = positional.remove(0);
+    let balloon = question_balloon(question, positional, default, list, timeout, rate)
+        .map_err(|err| format!("{err}\n{ASK_USAGE}"))?;
+    await_answer(balloon.with_source(source), hook)
+}
+
+/// A balloon asking `question`, with the choices as a list when asked for
+/// or when there are too many for buttons.
+fn question_balloon(
+    question: String,
+    choices: Vec<String>,
+    default: Option<String>,
+    list: bool,
+    timeout: Option<Duration>,
+    rate: f32,
+) -> Result<Balloon, String> {
+    if choices.len() < 2 {
+        return Err("a question needs at least two choices".to_string());
+    }
+    let default = match default {
+        Some(name) => Some(
+            choices
+                .iter()
+                .position(|choice| *choice == name)
+                .ok_or(format!("the default `{name}` is not one of the choices"))?,
+        ),
+        None => None,
+    };
+    let choices = if list || choices.len() > balloon::MAX_BUTTONS {
+        Choices::List(choices)
+    } else {
+        Choices::Buttons(choices)
+    };
+
+    Ok(Balloon::new(question, timeout.filter(|timeout| !timeout.is_zero()))
+        .with_choices(choices, default)
+        .with_reveal(rate))
+}
+
+/// `tiny-clippy input`: asks for a line of text and prints what was typed.
+pub fn input(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut timeout, mut hint, mut hook) = (None, INPUT_HINT.to_string(), None);
+    let mut rate = balloon::DEFAULT_REVEAL_RATE;
+    let mut source = "input".to_string();
+    let mut words = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+        match arg.as_str() {
+            "--timeout" => timeout = Some(parse_timeout(args.next())?),
+            "--rate" => rate = parse_rate(args.next())?,
+            "--hint" => hint = value("--hint")?,
+            "--hook" => hook = Some(value("--hook")?),
+            "--source" => source = value("--source")?,
+            "--help" | "-h" => return Err(INPUT_USAGE.to_string()),
+            flag if flag.starts_with("--") => {
+                return Err(format!("unknown option `{flag}`\n{INPUT_USAGE}"));
+            }
+            word => words.push(word.to_string()),
+        }
+    }
+
+    let prompt = if words.is_empty() { INPUT_PROMPT.to_string() } else { words.join(" ") };
+    let balloon = Balloon::new(prompt, timeout.filter(|timeout| !timeout.is_zero()))
+        .with_choices(Choices::Input(hint), None)
+        .with_reveal(rate)
+        .with_source(source);
+    await_answer(balloon, hook)
+}
+
+/// `tiny-clippy tip`: shows the next tip, on the topic given with `--tag`.
+pub fn tip(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let (mut tag, mut rate) = (None, balloon::DEFAULT_REVEAL_RATE);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tag" => tag = Some(args.next().ok_or("--tag needs a value")?),
+            "--rate" => rate = parse_rate(args.next())?,
+            "--help" | "-h" => return Err(TIP_USAGE.to_string()),
+            other => return Err(format!("unexpected argument `{other}`\n{TIP_USAGE}")),
+        }
+    }
+
+    let tips = Arc::new(Mutex::new(Tips::load(APP_NAME)));
+    if let Some(tag) = tag.as_ref().filter(|tag| !tips.lock().unwrap().tags().contains(tag)) {
+        return Err(format!("no tips are tagged `{tag}`"));
+    }
+    let balloon = tip_balloon(&tips, tag.as_deref(), rate).ok_or("every tip has been hidden")?;
+    launch(Some(balloon))
+}
+
+/// The next tip in a balloon whose buttons tone tips down, or `None` when
+/// there is nothing left to show on `topic`.
+fn tip_balloon(tips: &Arc<Mutex<Tips>>, topic: Option<&str>, rate: f32) -> Option<Balloon> {
+    let tip: Tip = tips.lock().unwrap().pick(topic)?;
+    let tips = tips.clone();
+    let choices = [TIP_THANKS, TIP_FEWER, TIP_NEVER].map(str::to_string).to_vec();
+    let balloon = Balloon::new(tip.text.clone(), Some(Balloon::reading_time(&tip.text)))
+        .with_choices(Choices::Buttons(choices), None)
+        .with_reveal(rate)
+        .with_source("tip")
+        .with_reply(Box::new(move |outcome| match outcome.answer() {
+            Some(TIP_FEWER) => tips.lock().unwrap().show_fewer_like(&tip),
+            Some(TIP_NEVER) => tips.lock().unwrap().never_show(&tip),
+            _ => {}
+        }));
+    Some(balloon)
+}
+
+/// Shows `balloon` until it goes away, prints its answer and runs `hook`
+/// with the answer in `CLIPPY_CHOICE` or `CLIPPY_INPUT`. Exits with status
+/// 1 when nothing answered it.
+fn await_answer(balloon: Balloon, hook: Option<String>) -> Result<(), String> {
+    let answer = Arc::new(Mutex::new(None));
+    let reply_to = answer.clone();
+    let balloon =
+        balloon.with_reply(Box::new(move |outcome| *reply_to.lock().unwrap() = Some(outcome)));
+    launch(Some(balloon))?;
+
+    let outcome = answer.lock().unwrap().take().unwrap_or(Outcome::Dismissed);
+    if let Some(answer) = outcome.answer() {
+        println!("{answer}");
+    }
+    if let Some(hook) = hook {
+        run_hook(&hook, &outcome)?;
+    }
+    if outcome.answer().is_none() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn parse_timeout(value: Option<String>) -> Result<Duration, String> {
+    let invalid = || "--timeout must be a number of seconds".to_string();
+    let secs: f32 = value.ok_or("--timeout needs a value")?.parse().map_err(|_| invalid())?;
+    check_timeout(secs).map_err(|err| format!("--{err}"))
+}
+
+fn check_timeout(secs: f32) -> Result<Duration, String> {
+    Duration::try_from_secs_f32(secs).map_err(|_| "timeout must be a number of seconds".to_string())
+}
+
+/// Runs `command` through the platform shell.
+fn shell(command: &str) -> Command {
+    #[cfg(windows)]
+    let mut shell = Command::new("cmd");
+    #[cfg(windows)]
+    shell.args(["/C", command]);
+    #[cfg(not(windows))]
+    let mut shell = Command::new("sh");
+    #[cfg(not(windows))]
+    shell.args(["-c", command]);
+    shell
+}
+
+fn parse_rate(value: Option<String>) -> Result<f32, String> {
+    let rate: f32 = value
+        .ok_or("--rate needs a value")?
+        .parse()
+        .map_err(|_| "--rate must be a number of characters per second".to_string())?;
+    check_rate(rate).map_err(|err| format!("--{err}"))
+}
+
+fn check_rate(rate: f32) -> Result<f32, String> {
+    if !rate.is_finite() || rate < 0.0 {
+        return Err("rate must be a number of characters per second".to_string());
+    }
+    Ok(rate)
+}
+
+fn run_hook(hook: &str, outcome: &Outcome) -> Result<(), String> {
+    let variable = match outcome {
+        Outcome::Submitted(_) => "CLIPPY_INPUT",
+        _ => "CLIPPY_CHOICE",
+    };
+
+    let status = shell(hook)
+        .env("CLIPPY_OUTCOME", outcome.kind())
+        .env(variable, outcome.answer().unwrap_or(""))
+        .status()
+        .map_err(|err| format!("failed to run hook: {err}"))?;
+    if !status.success() {
+        return Err(format!("hook failed: {status}"));
+    }
+    Ok(())
+}
+
+/// Hands typed text to the input handler in the background, so a slow
+/// command doesn't freeze Clippy.
+fn dispatch_input(handler: String, text: String) {
+    std::thread::spawn(move || {
+        if let Err(err) = shell(&handler).env("CLIPPY_INPUT", text).status() {
+            eprintln!("tiny-clippy: failed to run the input handler: {err}");
+        }
+    });
+}
+
+/// Opens the window, closing it again with `balloon` when one is given.
+fn launch(balloon: Option<Balloon>) -> Result<(), String> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_decorations(false)
+            .with_transparent(true)
+            .with_always_on_top()
+            .with_inner_size([FRAME_W as f32, FRAME_H as f32])
+            .with_resizable(false)
+            .with_mouse_passthrough(false),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        APP_NAME,
+        options,
+        Box::new(|cc| Ok(Box::new(ClippyApp::new(cc, balloon)))),
+    )
+    .map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+enum Zoom {
+    X1,
+    X2,
+    X3,
+    #[default]
+    FitDpi,
+}
+
+impl Zoom {
+    const ALL: [Zoom; 4] = [Zoom::X1, Zoom::X2, Zoom::X3, Zoom::FitDpi];
+
+    fn label(self) -> &'static str {
+        match self {
+            Zoom::X1 => "1x",
+            Zoom::X2 => "2x",
+            Zoom::X3 => "3x",
+            Zoom::FitDpi => "Fit to display",
+        }
+    }
+
+    /// Physical pixels per sprite pixel. Whole, so nearest-neighbour
+    /// sampling stays crisp at any display scale factor, unless linear
+    /// upscaling is on and fitting the display exactly looks better.
+    fn pixel_scale(self, pixels_per_point: f32, upscale: Upscale) -> f32 {
+        match self {
+            Zoom::X1 => 1.0,
+            Zoom::X2 => 2.0,
+            Zoom::X3 => 3.0,
+            Zoom::FitDpi if upscale == Upscale::Linear => pixels_per_point.max(1.0),
+            Zoom::FitDpi => pixels_per_point.round().max(1.0),
+        }
+    }
+
+    /// Size of one frame in points.
+    fn frame_size(self, pixels_per_point: f32, upscale: Upscale) -> egui::Vec2 {
+        let frame = egui::vec2(FRAME_W as f32, FRAME_H as f32);
+        frame * self.pixel_scale(pixels_per_point, upscale) / pixels_per_point
+    }
+}
+
+/// How often an idle Clippy brings up a tip. Off until asked for, since
+/// tips nobody wanted are exactly what he is remembered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+enum TipInterval {
+    #[default]
+    Never,
+    Minutes5,
+    Minutes15,
+    Hourly,
+}
+
+impl TipInterval {
+    const ALL: [TipInterval; 4] = [
+        TipInterval::Never,
+        TipInterval::Minutes5,
+        TipInterval::Minutes15,
+        TipInterval::Hourly,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            TipInterval::Never => "Never",
+            TipInterval::Minutes5 => "Every 5 minutes",
+            TipInterval::Minutes15 => "Every 15 minutes",
+            TipInterval::Hourly => "Every hour",
+        }
+    }
+
+    fn duration(self) -> Option<Duration> {
+        match self {
+            TipInterval::Never => None,
+            TipInterval::Minutes5 => Some(Duration::from_secs(5 * 60)),
+            TipInterval::Minutes15 => Some(Duration::from_secs(15 * 60)),
+            TipInterval::Hourly => Some(Duration::from_secs(60 * 60)),
+        }
+    }
+}
+
+struct ClippyApp {
+    animation: Animation,
+    textures: FrameTextures,
+    sprite_sheet: image::RgbaImage,
+    show_debug: bool,
+    perf: PerfStats,
+    history: History,
+    show_history: bool,
+    cursor_direction: Option<(LookDirection, Instant)>,
+    /// Whether the cursor can be followed outside the window, which takes
+    /// polling since no events come from there.
+    cursor_global: bool,
+    /// Whether the cursor was last seen within `CURSOR_NEAR` of Clippy.
+    cursor_near: bool,
+    hover: Option<(Instant, bool)>,
+    drag: Option<DragTracker>,
+    zoom: Zoom,
+    upscale: Upscale,
+    applied_layout: Option<AppliedLayout>,
+    placement: Option<Placed>,
+    /// Where the last move sent the window, and when.
+    moving_to: Option<(egui::Pos2, Instant)>,
+    click_through: bool,
+    passthrough: bool,
+    themes: Vec<Theme>,
+    theme: usize,
+    ghost: GhostMode,
+    opacity: f32,
+    target_opacity: f32,
+    last_fade_step: Instant,
+    balloon: Option<Balloon>,
+    /// Set for `tiny-clippy say`, `ask` and `input`, which are done once
+    /// their balloon is.
+    close_with_balloon: bool,
+    /// Whether the talking clip is looping while the balloon types.
+    talking: bool,
+    /// Shell command that gets text typed into the menu's question balloon
+    /// in `CLIPPY_INPUT`, such as a launcher or a local search.
+    input_handler: String,
+    /// Shared with the reply of the tip balloon on screen.
+    tips: Arc<Mutex<Tips>>,
+    tip_interval: TipInterval,
+    /// Tag tips are picked from; `None` picks from all of them.
+    tip_topic: Option<String>,
+    /// Only the Clippy that stays on the desktop listens.
+    control: Option<ControlServer>,
+    /// Hidden over the control socket: faded out and clicked through, but
+    /// still running so it can be shown again.
+    hidden: bool,
+}
+
+/// Where things sit in the viewport, in points. Without a balloon the
+/// sprite fills the viewport; with one the viewport grows to fit the
+/// balloon on whichever side of him it went.
+struct Layout {
+    size: egui::Vec2,
+    sprite: egui::Rect,
+    balloon: Option<(egui::Rect, Side)>,
+    /// How far Clippy is moved over from where he sits without the balloon.
+    nudge: egui::Vec2,
+}
+
+/// Where the balloon goes, and what that was worked out for.
+#[derive(Clone, Copy)]
+struct Placed {
+    /// Clippy's spot on the desktop, leaving out any nudge.
+    home: egui::Pos2,
+    frame_size: egui::Vec2,
+    body: egui::Vec2,
+    placement: Placement,
+}
+
+/// What was last sent to the window.
+#[derive(Clone, Copy, PartialEq)]
+struct AppliedLayout {
+    size: egui::Vec2,
+    sprite: egui::Pos2,
+    nudge: egui::Vec2,
+}
+
+/// Where frames live on the GPU. The whole sheet is uploaded once as an
+/// atlas when it fits the backend's texture limit and frames are shown as
+/// they are; with theme effects or an upscaler each frame is processed and
+/// uploaded on first use and kept around instead, at the pixel scale they
+/// were filtered for.
+enum FrameTextures {
+    Pending,
+    Atlas(egui::TextureHandle),
+    PerFrame(u32, HashMap<(u32, u32), CachedFrame>),
+}
+
+/// A processed frame's texture, with the alpha it was uploaded with kept
+/// for hit-testing, since effects and upscaling change what is opaque.
+struct CachedFrame {
+    texture: egui::TextureHandle,
+    alpha: image::GrayImage,
+}
+
+/// Follows a window drag handed to the OS with `StartDrag`. The button
+/// release often never reaches us, so the drag also counts as over once the
+/// window has stopped moving for a moment.
+struct DragTracker {
+    window_pos: Option<egui::Pos2>,
+    last_move: Instant,
+}
+
+impl ClippyApp {
+    fn new(cc: &eframe::CreationContext<'_>, balloon: Option<Balloon>) -> Self {
+        let sprite_sheet = load_sprite_sheet();
+
+        Self::setup_transparent_ui(&cc.egui_ctx);
+
+        let zoom = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, ZOOM_KEY))
+            .unwrap_or_default();
+        let upscale = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, UPSCALE_KEY))
+            .unwrap_or_default();
+        let click_through = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, CLICK_THROUGH_KEY))
+            .unwrap_or(true);
+        let themes = Theme::load(APP_NAME);
+        let theme = cc
+            .storage
+            .and_then(|storage| eframe::get_value::<String>(storage, THEME_KEY))
+            .and_then(|name| themes.iter().position(|theme| theme.name == name))
+            .unwrap_or(0);
+        let ghost = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, GHOST_KEY))
+            .unwrap_or_default();
+        let input_handler = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, INPUT_HANDLER_KEY))
+            .unwrap_or_default();
+        let tip_interval = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, TIP_INTERVAL_KEY))
+            .unwrap_or_default();
+        let tip_topic = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, TIP_TOPIC_KEY))
+            .unwrap_or_default();
+        let personality = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, PERSONALITY_KEY))
+            .unwrap_or_default();
+
+        let mut app = Self {
+            animation: Animation::new(ClipManifest::embedded(), personality),
+            textures: FrameTextures::Pending,
+            sprite_sheet,
+            show_debug: false,
+            perf: PerfStats::new(),
+            history: History::open(APP_NAME),
+            show_history: false,
+            cursor_direction: None,
+            cursor_global: false,
+            cursor_near: false,
+            hover: None,
+            drag: None,
+            zoom,
+            upscale,
+            applied_layout: None,
+            placement: None,
+            moving_to: None,
+            click_through,
+            passthrough: false,
+            themes,
+            theme,
+            ghost,
+            opacity: 1.0,
+            target_opacity: 1.0,
+            last_fade_step: Instant::now(),
+            balloon: None,
+            close_with_balloon: balloon.is_some(),
+            talking: false,
+            input_handler,
+            tips: Arc::new(Mutex::new(Tips::load(APP_NAME))),
+            tip_interval,
+            tip_topic,
+            control: None,
+            hidden: false,
+        };
+        if balloon.is_none() {
+            app.control = ControlServer::start(&cc.egui_ctx)
+                .map_err(|err| eprintln!("tiny-clippy: no control socket: {err}"))
+                .ok();
+        }
+        app.update_tip_interval();
+        if let Some(balloon) = balloon {
+            app.show_balloon(&cc.egui_ctx, balloon);
+        }
+        app
+    }
+
+    /// Replaces any balloon already up, which counts as dismissing it.
+    fn show_balloon(&mut self, ctx: &egui::Context, balloon: Balloon) {
+        self.finish_balloon(Outcome::Dismissed);
+        self.notify("balloon_shown", json!({ "source": balloon.source(), "text": balloon.text() }));
+        if balloon.has_input() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+        }
+        if balloon.revealing() {
+            self.animation.talk_until_done();
+            self.talking = true;
+        } else {
+            self.animation.talk();
+        }
+        self.balloon = Some(balloon);
+    }
+
+    /// Lets the talking clip finish once the balloon has typed everything
+    /// out, or has gone away.
+    fn update_talking(&mut self) {
+        if self.talking && !self.balloon.as_ref().is_some_and(Balloon::revealing) {
+            self.animation.done_talking();
+            self.talking = false;
+        }
+    }
+
+    /// Tips only come up in the Clippy that stays on the desktop, not in
+    /// the one `say` or `ask` opened.
+    fn update_tip_interval(&mut self) {
+        let interval = self.tip_interval.duration().filter(|_| !self.close_with_balloon);
+        self.animation.set_tip_interval(interval);
+    }
+
+    fn show_tip(&mut self, ctx: &egui::Context) {
+        let topic = self.tip_topic.as_deref();
+        if let Some(balloon) = tip_balloon(&self.tips, topic, balloon::DEFAULT_REVEAL_RATE) {
+            self.show_balloon(ctx, balloon);
+            ctx.request_repaint();
+        }
+    }
+
+    /// Asks what to do and passes the answer to the input handler.
+    fn ask_for_input(&mut self, ctx: &egui::Context) {
+        let handler = self.input_handler.clone();
+        let balloon = Balloon::new(INPUT_PROMPT.to_string(), None)
+            .with_choices(Choices::Input(INPUT_HINT.to_string()), None)
+            .with_reveal(balloon::DEFAULT_REVEAL_RATE)
+            .with_source("menu")
+            .with_reply(Box::new(move |outcome| {
+                if let Outcome::Submitted(text) = outcome {
+                    dispatch_input(handler, text);
+                }
+            }));
+        self.show_balloon(ctx, balloon);
+    }
+
+    /// Notes the balloon in the history and tells whoever is waiting on it.
+    fn finish_balloon(&mut self, outcome: Outcome) {
+        let Some(balloon) = self.balloon.take() else {
+            return;
+        };
+        let now = SystemTime::now();
+        let shown = now.checked_sub(balloon.shown_at().elapsed()).unwrap_or(now);
+        let entry = history::Entry::new(balloon.source(), balloon.text(), shown, &outcome);
+        self.notify("balloon_closed", serde_json::to_value(&entry).unwrap_or_default());
+        self.history.record(entry);
+        balloon.finish(outcome);
+    }
+
+    /// Tells control socket clients about `event`.
+    fn notify(&self, event: &str, body: serde_json::Value) {
+        if let Some(control) = &self.control {
+            control.notify(event, body);
+        }
+    }
+
+    fn handle_requests(&mut self, ctx: &egui::Context) {
+        let requests: Vec<control::Request> =
+            self.control.iter().flat_map(ControlServer::requests).collect();
+        for request in requests {
+            self.handle_request(ctx, request);
+        }
+    }
+
+    /// Carries out a command from the control socket. Questions are
+    /// answered once the user has, everything else right away.
+    fn handle_request(&mut self, ctx: &egui::Context, request: control::Request) {
+        let control::Request { command, reply } = request;
+        match command {
+            control::Command::Play { clip } => match self.animation.manifest.find(&clip) {
+                Some(def) => {
+                    self.animation.play_next(def.clip());
+                    reply.ok(json!({}));
+                }
+                None => reply.error(format!("no clip is named `{clip}`")),
+            },
+            control::Command::Say { text, timeout, rate, source } => {
+                let balloon = (|| {
+                    let timeout = timeout.map(check_timeout).transpose()?;
+                    let rate = check_rate(rate.unwrap_or(balloon::DEFAULT_REVEAL_RATE))?;
+                    Ok::<_, String>(say_balloon(text, timeout, rate))
+                })();
+                match balloon {
+                    Ok(balloon) => {
+                        self.hidden = false;
+                        let source = source.unwrap_or_else(|| "socket".to_string());
+                        self.show_balloon(ctx, balloon.with_source(source));
+                        reply.ok(json!({}));
+                    }
+                    Err(err) => reply.error(err),
+                }
+            }
+            control::Command::Ask { text, choices, default, timeout, list, rate, source } => {
+                let balloon = (|| {
+                    let timeout = timeout.map(check_timeout).transpose()?;
+                    let rate = check_rate(rate.unwrap_or(balloon::DEFAULT_REVEAL_RATE))?;
+                    question_balloon(text, choices, default, list, timeout, rate)
+                })();
+                match balloon {
+                    Ok(balloon) => {
+                        self.hidden = false;
+                        let balloon = balloon
+                            .with_source(source.unwrap_or_else(|| "socket".to_string()))
+                            .with_reply(Box::new(move |outcome| {
+                                reply.ok(json!({
+                                    "outcome": outcome.kind(),
+                                    "answer": outcome.answer(),
+                                }));
+                            }));
+                        self.show_balloon(ctx, balloon);
+                    }
+                    Err(err) => reply.error(err),
+                }
+            }
+            control::Command::Move { x, y } => match self.applied_layout {
+                Some(applied) => {
+                    let target = egui::pos2(x, y) - applied.sprite.to_vec2() + applied.nudge;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(target));
+                    self.moving_to = Some((target, Instant::now()));
+                    reply.ok(json!({}));
+                }
+                None => reply.error("Clippy isn't on screen yet"),
+            },
+            control::Command::Hide | control::Command::Show => {
+                self.hidden = matches!(command, control::Command::Hide);
+                reply.ok(json!({}));
+            }
+            control::Command::State => reply.ok(self.state(ctx)),
+            control::Command::Quit => {
+                reply.ok(json!({}));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+            }
+        }
+        ctx.request_repaint();
+    }
+
+    /// What `state` answers over the control socket.
+    fn state(&self, ctx: &egui::Context) -> serde_json::Value {
+        let window = ctx.input(|i| i.viewport().inner_rect);
+        let position = self.applied_layout.zip(window).map(|(applied, window)| {
+            let home = window.min + applied.sprite.to_vec2() - applied.nudge;
+            [home.x, home.y]
+        });
+        json!({
+            "visible": !self.hidden,
+            "state": self.animation.get_current_state_info(),
+            "playing": self.animation.is_playing(),
+            "quiet": self.animation.quiet,
+            "personality": self.animation.personality.label(),
+            "position": position,
+            "balloon": self.balloon.as_ref().map(|balloon| {
+                json!({ "source": balloon.source(), "text": balloon.text() })
+            }),
+        })
+    }
+
+    fn close_balloon(&mut self, ctx: &egui::Context, outcome: Outcome) {
+        self.finish_balloon(outcome);
+        if self.close_with_balloon {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        // Shrink the viewport back without waiting for the next frame.
+        ctx.request_repaint();
+    }
+
+    /// The cursor in viewport points, read once per frame. Uses the global
+    /// position where the platform has one, so it keeps working outside the
+    /// window and while mouse passthrough is on.
+    fn read_cursor(&mut self, ctx: &egui::Context) -> Option<egui::Pos2> {
+        let global = cursor::global_position(ctx.pixels_per_point());
+        self.cursor_global = global.is_some();
+        match global {
+            Some(pos) => {
+                let window = ctx.input(|i| i.viewport().inner_rect)?;
+                Some(pos - window.min.to_vec2())
+            }
+            None => ctx.input(|i| i.pointer.latest_pos()),
+        }
+    }
+
+    /// Fades Clippy towards the ghost opacity for the cursor's distance.
+    fn update_ghost(&mut self, cursor: Option<egui::Pos2>, sprite: egui::Rect) {
+        let distance = cursor.map(|pos| sprite.distance_to_pos(pos));
+        self.cursor_near = distance.is_some_and(|distance| distance <= CURSOR_NEAR);
+        self.target_opacity = if self.hidden { 0.0 } else { self.ghost.target_opacity(distance) };
+
+        let now = Instant::now();
+        let secs = now.duration_since(self.last_fade_step).as_secs_f32();
+        self.opacity = ghost::approach(self.opacity, self.target_opacity, secs);
+        self.last_fade_step = now;
+    }
+
+    fn set_theme(&mut self, theme: usize) {
+        if theme != self.theme {
+            self.theme = theme;
+            self.textures = FrameTextures::Pending;
+        }
+    }
+
+    fn set_upscale(&mut self, upscale: Upscale) {
+        if upscale != self.upscale {
+            self.upscale = upscale;
+            self.textures = FrameTextures::Pending;
+        }
+    }
+
+    /// Whether the sprite pixel under `pos` (viewport points) is solid enough
+    /// to count as Clippy rather than the transparent box around him.
+    fn is_opaque(
+        &self,
+        (frame_x, frame_y): (u32, u32),
+        pos: egui::Pos2,
+        sprite: egui::Rect,
+    ) -> bool {
+        let local = pos - sprite.min;
+        let (u, v) = (local.x / sprite.width(), local.y / sprite.height());
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return false;
+        }
+        let texel = |width: u32, height: u32| {
+            let x = ((u * width as f32) as u32).min(width - 1);
+            let y = ((v * height as f32) as u32).min(height - 1);
+            (x, y)
+        };
+
+        let alpha = match &self.textures {
+            // Hit-tests what was actually uploaded, shadow and outline
+            // included.
+            FrameTextures::PerFrame(_, cache) => {
+                let Some(cached) = cache.get(&(frame_x, frame_y)) else {
+                    return false;
+                };
+                let (x, y) = texel(cached.alpha.width(), cached.alpha.height());
+                cached.alpha.get_pixel(x, y)[0]
+            }
+            _ => {
+                let (x, y) = texel(FRAME_W, FRAME_H);
+                self.sprite_sheet.get_pixel(frame_x * FRAME_W + x, frame_y * FRAME_H + y)[3]
+            }
+        };
+        alpha >= HIT_ALPHA
+    }
+
+    /// Lets clicks fall through the window while the cursor is over a
+    /// transparent pixel or the empty space beside the balloon. With
+    /// passthrough on the window gets no pointer events, so the cursor is
+    /// polled globally until it reaches something opaque or leaves the window.
+    fn update_click_through(
+        &mut self,
+        ctx: &egui::Context,
+        cursor: Option<egui::Pos2>,
+        frame: (u32, u32),
+        layout: &Layout,
+    ) {
+        let over_transparent = cursor.is_some_and(|pos| {
+            egui::Rect::from_min_size(egui::Pos2::ZERO, layout.size).contains(pos)
+                && !layout.balloon.is_some_and(|(balloon, _)| balloon.contains(pos))
+                && !self.is_opaque(frame, pos, layout.sprite)
+        });
+        let wanted = self.hidden
+            || ((self.click_through && over_transparent)
+                || self.ghost.wants_click_through(self.opacity))
+                && self.drag.is_none()
+                && !self.show_debug
+                && !self.show_history
+                && !ctx.memory(|mem| mem.any_popup_open())
+                && self.cursor_global;
+
+        if wanted != self.passthrough {
+            ctx.send_viewport_cmd(egui::ViewportCommand::MousePassthrough(wanted));
+            self.passthrough = wanted;
+        }
+    }
+
+    /// Works out which side of Clippy a balloon of size `body` goes on,
+    /// from where he sits on his monitor. Only redone once he or the
+    /// balloon has changed, and not while a drag or one of our own moves
+    /// is still under way, since the window's position lags behind those.
+    fn update_placement(
+        &mut self,
+        ctx: &egui::Context,
+        frame_size: egui::Vec2,
+        body: Option<egui::Vec2>,
+    ) {
+        let Some(body) = body else {
+            self.placement = None;
+            return;
+        };
+
+        if let Some((target, since)) = self.moving_to {
+            let window = ctx.input(|i| i.viewport().outer_rect);
+            let arrived = window.is_some_and(|window| window.min.distance(target) < 1.0);
+            if arrived || since.elapsed() >= Duration::from_millis(MOVE_SETTLE_MS) {
+                self.moving_to = None;
+            }
+        }
+        if self.drag.is_some() {
+            // Wherever he is dropped becomes his spot, rather than snapping
+            // back by the nudge once the balloon goes.
+            if let (Some(applied), Some(placed)) = (&mut self.applied_layout, &mut self.placement)
+            {
+                applied.nudge = egui::Vec2::ZERO;
+                placed.placement.nudge = egui::Vec2::ZERO;
+            }
+        }
+        let settling = self.moving_to.is_some() || self.drag.is_some();
+        if settling && self.placement.is_some() {
+            return;
+        }
+
+        let window = ctx.input(|i| i.viewport().inner_rect);
+        let home = match (window, self.applied_layout) {
+            (Some(window), Some(applied)) => window.min + applied.sprite.to_vec2() - applied.nudge,
+            _ => egui::Pos2::ZERO,
+        };
+        // Window positions come back rounded to whole pixels.
+        let unchanged = |placed: Placed| {
+            placed.home.distance(home) < 1.0
+                && placed.frame_size == frame_size
+                && placed.body == body
+        };
+        if self.placement.is_some_and(unchanged) {
+            return;
+        }
+
+        let sprite = egui::Rect::from_min_size(home, frame_size);
+
+        let monitor = window.and_then(|_| {
+            monitor::bounds_at(sprite.center(), ctx.pixels_per_point()).or_else(|| {
+                let size = ctx.input(|i| i.viewport().monitor_size)?;
+                Some(egui::Rect::from_min_size(egui::Pos2::ZERO, size))
+            })
+        });
+        let placement = balloon::place(body, sprite, monitor);
+        self.placement = Some(Placed { home, frame_size, body, placement });
+    }
+
+    fn layout(&self, frame_size: egui::Vec2) -> Layout {
+        let Some(Placed { home, placement, .. }) = self.placement else {
+            return Layout {
+                size: frame_size,
+                sprite: egui::Rect::from_min_size(egui::Pos2::ZERO, frame_size),
+                balloon: None,
+                nudge: egui::Vec2::ZERO,
+            };
+        };
+
+        let sprite = egui::Rect::from_min_size(home, frame_size);
+        let both = sprite.union(placement.body);
+        let offset = egui::Pos2::ZERO - both.min;
+        Layout {
+            size: both.size(),
+            sprite: sprite.translate(offset),
+            balloon: Some((placement.body.translate(offset), placement.side)),
+            nudge: placement.nudge,
+        }
+    }
+
+    /// Resizes the viewport to the zoomed frame and any balloon, moving the
+    /// window so Clippy himself stays put on screen, or moves over by the
+    /// nudge while the balloon needs him to. Runs every update since the
+    /// scale factor changes when the window moves to another monitor.
+    fn apply_layout(&mut self, ctx: &egui::Context) -> Layout {
+        let frame_size = self.zoom.frame_size(ctx.pixels_per_point(), self.upscale);
+        let body = self.balloon.as_ref().map(|balloon| balloon.size(ctx));
+        self.update_placement(ctx, frame_size, body);
+        let layout = self.layout(frame_size);
+        let applied = AppliedLayout {
+            size: layout.size,
+            sprite: layout.sprite.min,
+            nudge: layout.nudge,
+        };
+        if self.applied_layout == Some(applied) {
+            return layout;
+        }
+
+        if let Some(old) = self.applied_layout {
+            let shift = (applied.sprite - old.sprite) - (applied.nudge - old.nudge);
+            let window = ctx.input(|i| i.viewport().outer_rect);
+            if let (true, Some(window)) = (shift != egui::Vec2::ZERO, window) {
+                let target = window.min - shift;
+                ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(target));
+                self.moving_to = Some((target, Instant::now()));
+            }
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(layout.size));
+        self.applied_layout = Some(applied);
+        layout
+    }
+
+    fn look_direction(offset: egui::Vec2) -> Option<LookDirection> {
+        if !(LOOK_DEAD_ZONE..=LOOK_RADIUS).contains(&offset.length()) {
+            return None;
+        }
+
+        if offset.x.abs() >= offset.y.abs() {
+            if offset.x < 0.0 {
+                Some(LookDirection::Left)
+            } else {
+                Some(LookDirection::Right)
+            }
+        } else if offset.y < 0.0 {
+            Some(LookDirection::Up)
+        } else {
+            Some(LookDirection::Down)
+        }
+    }
+
+    /// Follows the cursor across the whole desktop where the platform
+    /// reports it globally, and otherwise only while it is over the window.
+    fn track_cursor(&mut self, cursor: Option<egui::Pos2>, sprite: egui::Rect) {
+        let center = sprite.center();
+        let direction = cursor.and_then(|pos| Self::look_direction(pos - center));
+
+        let Some(direction) = direction else {
+            self.cursor_direction = None;
+            self.animation.look_at(None);
+            return;
+        };
+
+        match self.cursor_direction {
+            Some((current, since)) if current == direction => {
+                if since.elapsed() >= Duration::from_millis(LOOK_LINGER_MS) {
+                    self.animation.look_at(Some(direction));
+                }
+            }
+            _ => self.cursor_direction = Some((direction, Instant::now())),
+        }
+    }
+
+    /// The earliest moment anything on screen can change without new input:
+    /// the next animation frame or typed character, or a pending look, hover,
+    /// drag or balloon timeout.
+    fn next_wakeup(&self) -> Instant {
+        let mut wakeup = self.animation.next_change();
+
+        if let Some((_, since)) = self.cursor_direction {
+            let look_due = since + Duration::from_millis(LOOK_LINGER_MS);
+            if look_due > Instant::now() {
+                wakeup = wakeup.min(look_due);
+            }
+        }
+        if self.cursor_global || self.ghost.enabled {
+            let poll = if self.cursor_near { CURSOR_POLL_MS } else { CURSOR_IDLE_POLL_MS };
+            wakeup = wakeup.min(Instant::now() + Duration::from_millis(poll));
+        }
+        if let Some((since, false)) = self.hover {
+            wakeup = wakeup.min(since + Duration::from_millis(HOVER_REACT_MS));
+        }
+        if let Some(drag) = &self.drag {
+            wakeup = wakeup.min(drag.last_move + Duration::from_millis(DRAG_SETTLE_MS));
+        }
+        if let Some(expires_at) = self.balloon.as_ref().and_then(Balloon::expires_at) {
+            wakeup = wakeup.min(expires_at);
+        }
+        if let Some(next_reveal) = self.balloon.as_ref().and_then(Balloon::next_reveal) {
+            wakeup = wakeup.min(next_reveal);
+        }
+        if self.passthrough && !self.hidden {
+            wakeup = wakeup.min(Instant::now() + Duration::from_millis(CLICK_THROUGH_POLL_MS));
+        }
+        if self.opacity != self.target_opacity {
+            wakeup = Instant::now();
+        }
+
+        wakeup
+    }
+
+    fn handle_interactions(&mut self, ctx: &egui::Context, response: &egui::Response) {
+        if response.double_clicked() {
+            self.animation.react(Interaction::DoubleClick);
+            self.notify("double_clicked", json!({}));
+        } else if response.clicked() {
+            self.animation.react(Interaction::Click);
+            self.notify("clicked", json!({}));
+        }
+
+        if response.drag_started() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
+            self.animation.react(Interaction::DragStart);
+            self.drag = Some(DragTracker {
+                window_pos: None,
+                last_move: Instant::now(),
+            });
+        }
+
+        if let Some(drag) = &mut self.drag {
+            let window_pos = ctx.input(|i| i.viewport().outer_rect).map(|rect| rect.min);
+            if window_pos != drag.window_pos {
+                drag.window_pos = window_pos;
+                drag.last_move = Instant::now();
+            }
+
+            let settled = drag.last_move.elapsed() >= Duration::from_millis(DRAG_SETTLE_MS);
+            if response.drag_stopped() || settled {
+                self.drag = None;
+                self.animation.react(Interaction::DragEnd);
+                self.notify("dragged", json!({}));
+            }
+        }
+
+        if response.hovered() && self.drag.is_none() {
+            let (since, reacted) = self.hover.get_or_insert((Instant::now(), false));
+            if !*reacted && since.elapsed() >= Duration::from_millis(HOVER_REACT_MS) {
+                *reacted = true;
+                self.animation.react(Interaction::Hover);
+            }
+        } else {
+            self.hover = None;
+        }
+    }
+
+    fn setup_transparent_ui(ctx: &egui::Context) {
+        let mut visuals = egui::Visuals::dark();
+        visuals.window_fill = egui::Color32::TRANSPARENT;
+        visuals.panel_fill = egui::Color32::TRANSPARENT;
+        ctx.set_visuals(visuals);
+    }
+
+    /// The backend only reports its texture limit once frames start, so the
+    /// atlas is uploaded on the first update rather than in `new`.
+    fn upload_textures(&mut self, ctx: &egui::Context) -> FrameTextures {
+        let max_side = ctx.input(|i| i.max_texture_side) as u32;
+        let too_big = self.sprite_sheet.width() > max_side || self.sprite_sheet.height() > max_side;
+        let processed = !self.themes[self.theme].effects.is_empty() || self.upscale.smooth();
+        // Linear sampling would also bleed neighbouring frames into an atlas.
+        if too_big || processed {
+            return FrameTextures::PerFrame(self.filter_scale(ctx), HashMap::new());
+        }
+
+        let started = Instant::now();
+        let size = [self.sprite_sheet.width() as usize, self.sprite_sheet.height() as usize];
+        let sheet = egui::ColorImage::from_rgba_unmultiplied(size, self.sprite_sheet.as_raw());
+        let atlas = ctx.load_texture("clippy-sheet", sheet, egui::TextureOptions::NEAREST);
+        self.perf.uploaded(started.elapsed());
+        FrameTextures::Atlas(atlas)
+    }
+
+    /// How many screen pixels per frame pixel the upscaler's output has to
+    /// cover. Filters are brought all the way there on the CPU, since
+    /// stretching their output on the GPU would blur away what they drew;
+    /// `Linear` and `Nearest` leave the stretching to the GPU.
+    fn filter_scale(&self, ctx: &egui::Context) -> u32 {
+        match self.upscale {
+            Upscale::Nearest | Upscale::Linear => 1,
+            upscale => self.zoom.pixel_scale(ctx.pixels_per_point(), upscale) as u32,
+        }
+    }
+
+    /// The texture holding a frame and the UV rectangle that frame covers.
+    fn frame_texture(
+        &mut self,
+        ctx: &egui::Context,
+        frame_x: u32,
+        frame_y: u32,
+    ) -> (egui::TextureId, egui::Rect) {
+        let scale = self.filter_scale(ctx);
+        if matches!(self.textures, FrameTextures::PerFrame(cached, _) if cached != scale) {
+            self.textures = FrameTextures::Pending;
+        }
+        if matches!(self.textures, FrameTextures::Pending) {
+            self.textures = self.upload_textures(ctx);
+        }
+
+        match &mut self.textures {
+            FrameTextures::Atlas(atlas) => {
+                let [width, height] = atlas.size();
+                let uv_size = egui::vec2(
+                    FRAME_W as f32 / width as f32,
+                    FRAME_H as f32 / height as f32,
+                );
+                let uv_min = egui::pos2(frame_x as f32 * uv_size.x, frame_y as f32 * uv_size.y);
+                (atlas.id(), egui::Rect::from_min_size(uv_min, uv_size))
+            }
+            FrameTextures::PerFrame(scale, cache) => {
+                let sprite_sheet = &self.sprite_sheet;
+                let effects = &self.themes[self.theme].effects;
+                let (upscale, scale) = (self.upscale, *scale);
+                let perf = &mut self.perf;
+                let cached = cache.entry((frame_x, frame_y)).or_insert_with(|| {
+                    let started = Instant::now();
+                    let mut frame = extract_frame(sprite_sheet, frame_x, frame_y);
+                    perf.extracted(started.elapsed());
+                    effects::apply(effects, &mut frame);
+                    let frame = upscale::to_scale(upscale, &frame, scale);
+
+                    let size = [frame.width() as usize, frame.height() as usize];
+                    let color_image =
+                        egui::ColorImage::from_rgba_unmultiplied(size, frame.as_raw());
+                    let alpha = image::GrayImage::from_fn(frame.width(), frame.height(), |x, y| {
+                        image::Luma([frame.get_pixel(x, y)[3]])
+                    });
+                    let texture = ctx.load_texture(
+                        format!("clippy-frame-{frame_x}-{frame_y}"),
+                        color_image,
+                        // Filtered frames are already at screen size.
+                        if upscale == Upscale::Linear {
+                            egui::TextureOptions::LINEAR
+                        } else {
+                            egui::TextureOptions::NEAREST
+                        },
+                    );
+                    perf.uploaded(started.elapsed());
+                    CachedFrame { texture, alpha }
+                });
+                let full = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                (cached.texture.id(), full)
+            }
+            FrameTextures::Pending => unreachable!("textures are uploaded above"),
+        }
+    }
+}
+
+impl eframe::App for ClippyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.perf.begin_update();
+        if ctx.input(|i| i.viewport().close_requested()) {
+            // Dropping the balloon would dismiss it without a history entry.
+            self.finish_balloon(Outcome::Dismissed);
+            self.notify("closing", json!({}));
+        }
+        self.handle_requests(ctx);
+        if let Some(outcome) = self.balloon.as_ref().and_then(Balloon::timed_out) {
+            self.close_balloon(ctx, outcome);
+        }
+        self.update_talking();
+        let layout = self.apply_layout(ctx);
+        let cursor = self.read_cursor(ctx);
+        self.track_cursor(cursor, layout.sprite);
+        self.update_ghost(cursor, layout.sprite);
+        let (frame_x, frame_y) = self.animation.update();
+        if self.balloon.is_none() && !self.hidden && self.animation.take_tip_due() {
+            self.show_tip(ctx);
+        }
+        self.perf.shown((frame_x, frame_y));
+        let (texture_id, uv) = self.frame_texture(ctx, frame_x, frame_y);
+
+        let fading = self
+            .animation
+            .fading_frame()
+            .map(|((fade_x, fade_y), opacity)| (self.frame_texture(ctx, fade_x, fade_y), opacity));
+
+        let (response, balloon_outcome) = egui::CentralPanel::default()
+            .frame(egui::Frame::none())
+            .show(ctx, |ui| {
+                let balloon_outcome = match (&mut self.balloon, layout.balloon) {
+                    (Some(balloon), Some((body, side))) => {
+                        balloon.show(ui, body, layout.sprite, side, self.opacity)
+                    }
+                    _ => None,
+                };
+
+                let img_widget = egui::Image::new((texture_id, layout.sprite.size()))
+                    .uv(uv)
+                    .tint(egui::Color32::from_white_alpha((self.opacity * 255.0) as u8))
+                    .sense(egui::Sense::click_and_drag());
+
+                let response = ui.put(layout.sprite, img_widget);
+
+                if let Some(((fade_id, fade_uv), opacity)) = fading {
+                    ui.painter().image(
+                        fade_id,
+                        response.rect,
+                        fade_uv,
+                        egui::Color32::from_white_alpha((opacity * self.opacity * 255.0) as u8),
+                    );
+                }
+
+                response.context_menu(|ui| {
+                    ui.label(self.animation.get_current_state_info());
+
+                    if let Some(time_left) = self.animation.time_until_next_animation() {
+                        ui.label(format!("Next in: {:.1}s", time_left.as_secs_f32()));
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Stop animation").clicked() {
+                        self.animation.interrupt();
+                        ui.close_menu();
+                    }
+
+                    let has_handler = !self.input_handler.trim().is_empty();
+                    let ask = ui
+                        .add_enabled(has_handler, egui::Button::new("What would you like to do?"))
+                        .on_disabled_hover_text("Set a question handler first.");
+                    if ask.clicked() {
+                        self.ask_for_input(ctx);
+                        ui.close_menu();
+                    }
+
+                    let mut quiet = self.animation.quiet;
+                    if ui.checkbox(&mut quiet, "Quiet mode").changed() {
+                        self.animation.set_quiet(quiet);
+                    }
+
+                    ui.menu_button("Personality", |ui| {
+                        for personality in Personality::ALL {
+                            let selected = self.animation.personality == personality;
+                            if ui.radio(selected, personality.label()).clicked() {
+                                self.animation.set_personality(personality);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.menu_button("Zoom", |ui| {
+                        for zoom in Zoom::ALL {
+                            if ui.radio(self.zoom == zoom, zoom.label()).clicked() {
+                                self.zoom = zoom;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.menu_button("Upscaling", |ui| {
+                        for upscale in Upscale::ALL {
+                            if ui.radio(self.upscale == upscale, upscale.label()).clicked() {
+                                self.set_upscale(upscale);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.menu_button("Theme", |ui| {
+                        for index in 0..self.themes.len() {
+                            let name = &self.themes[index].name;
+                            if ui.radio(self.theme == index, name.as_str()).clicked() {
+                                self.set_theme(index);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    ui.checkbox(&mut self.click_through, "Click through transparent areas");
+
+                    ui.menu_button("Ghost mode", |ui| {
+                        ui.checkbox(&mut self.ghost.enabled, "Fade when the cursor is near");
+                        ui.add(
+                            egui::Slider::new(&mut self.ghost.opacity, 0.0..=1.0)
+                                .text("Faded opacity"),
+                        );
+                        ui.add(
+                            egui::Slider::new(&mut self.ghost.radius, 0.0..=300.0).text("Radius"),
+                        );
+                        ui.checkbox(&mut self.ghost.click_through, "Click through while faded");
+                    });
+                    ui.menu_button("Tips", |ui| {
+                        if ui.button("Show a tip now").clicked() {
+                            self.show_tip(ctx);
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        for interval in TipInterval::ALL {
+                            if ui.radio(self.tip_interval == interval, interval.label()).clicked()
+                            {
+                                self.tip_interval = interval;
+                                self.update_tip_interval();
+                            }
+                        }
+                        ui.separator();
+                        ui.radio_value(&mut self.tip_topic, None, "Any topic");
+                        let tags = self.tips.lock().unwrap().tags().to_vec();
+                        for tag in tags {
+                            let label = tag.clone();
+                            ui.radio_value(&mut self.tip_topic, Some(tag), label);
+                        }
+                        ui.separator();
+                        if ui.button("Show hidden tips again").clicked() {
+                            self.tips.lock().unwrap().reset();
+                            ui.close_menu();
+                        }
+                    });
+                    ui.menu_button("Question handler", |ui| {
+                        ui.label("Shell command run with the question in $CLIPPY_INPUT:");
+                        ui.text_edit_singleline(&mut self.input_handler);
+                    });
+                    if ui.checkbox(&mut self.show_history, "Show history").changed()
+                        && self.show_history
+                    {
+                        self.history.reload();
+                    }
+                    ui.checkbox(&mut self.show_debug, "Show debug info");
+
+                    ui.separator();
+
+                    if ui.button("Close Clippy").clicked() {
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+                });
+
+                if self.show_history {
+                    egui::Window::new("History")
+                        .collapsible(false)
+                        .resizable(false)
+                        .open(&mut self.show_history)
+                        .show(ctx, |ui| self.history.show(ui));
+                }
+
+                if self.show_debug {
+                    egui::Window::new("Debug")
+                        .collapsible(false)
+                        .resizable(false)
+                        .show(ctx, |ui| {
+                            ui.label(self.animation.get_current_state_info());
+                            if let Some(time_left) = self.animation.time_until_next_animation() {
+                                ui.label(format!("Cooldown: {:.1}s", time_left.as_secs_f32()));
+                            }
+                            let mood = self.animation.mood;
+                            ui.label(format!(
+                                "Energy {:.2}, boredom {:.2}, annoyance {:.2}",
+                                mood.energy, mood.boredom, mood.annoyance
+                            ));
+
+                            ui.separator();
+                            let target_fps = if self.animation.is_playing() {
+                                1000.0 / FRAME_DURATION_MS as f32
+                            } else {
+                                0.0
+                            };
+                            self.perf.show(ui, target_fps);
+                        });
+                }
+
+                (response, balloon_outcome)
+            })
+            .inner;
+
+        if let Some(outcome) = balloon_outcome {
+            self.close_balloon(ctx, outcome);
+        }
+        self.handle_interactions(ctx, &response);
+        self.update_click_through(ctx, cursor, (frame_x, frame_y), &layout);
+
+        let mut repaint_delay = self.next_wakeup().saturating_duration_since(Instant::now());
+        if self.show_debug {
+            // Keep the countdowns in the debug window ticking.
+            repaint_delay = repaint_delay.min(Duration::from_millis(DEBUG_REFRESH_MS));
+        }
+
+        ctx.request_repaint_after(repaint_delay);
+        self.perf.end_update();
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, ZOOM_KEY, &self.zoom);
+        eframe::set_value(storage, UPSCALE_KEY, &self.upscale);
+        eframe::set_value(storage, CLICK_THROUGH_KEY, &self.click_through);
+        eframe::set_value(storage, THEME_KEY, &self.themes[self.theme].name);
+        eframe::set_value(storage, GHOST_KEY, &self.ghost);
+        eframe::set_value(storage, INPUT_HANDLER_KEY, &self.input_handler);
+        eframe::set_value(storage, TIP_INTERVAL_KEY, &self.tip_interval);
+        eframe::set_value(storage, TIP_TOPIC_KEY, &self.tip_topic);
+        eframe::set_value(storage, PERSONALITY_KEY, &self.animation.personality);
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        [0.0, 0.0, 0.0, 0.0]
+    }
+}
\ No newline at end of file
@@ -0,0 +1,89 @@
+//! Which window Clippy lives in.
+//!
+//! eframe is the default and carries every feature: menus, zoom, themes,
+//! ghost mode. The other backends are cargo features for machines where GL
+//! is unavailable or too heavy; they only need to show a frame and report
+//! clicks and drags, which is what `window::Window` asks of them.
+
+#[cfg(feature = "minifb")]
+mod minifb;
+#[cfg(feature = "pixels")]
+mod pixels;
+#[cfg(feature = "softbuffer")]
+mod softbuffer;
+#[cfg(any(feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+mod window;
+#[cfg(any(feature = "pixels", feature = "softbuffer"))]
+mod winit;
+
+#[cfg(any(feature = "pixels", feature = "softbuffer"))]
+use winit::WinitWindow;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    #[cfg(feature = "eframe")]
+    Eframe,
+    #[cfg(feature = "minifb")]
+    Minifb,
+    #[cfg(feature = "pixels")]
+    Pixels,
+    #[cfg(feature = "softbuffer")]
+    Softbuffer,
+}
+
+impl Backend {
+    /// Every backend compiled into this binary, default first.
+    pub const ALL: &'static [Backend] = &[
+        #[cfg(feature = "eframe")]
+        Backend::Eframe,
+        #[cfg(feature = "softbuffer")]
+        Backend::Softbuffer,
+        #[cfg(feature = "minifb")]
+        Backend::Minifb,
+        #[cfg(feature = "pixels")]
+        Backend::Pixels,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "eframe")]
+            Backend::Eframe => "eframe",
+            #[cfg(feature = "minifb")]
+            Backend::Minifb => "minifb",
+            #[cfg(feature = "pixels")]
+            Backend::Pixels => "pixels",
+            #[cfg(feature = "softbuffer")]
+            Backend::Softbuffer => "softbuffer",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        Self::ALL.iter().copied().find(|backend| backend.name() == name).ok_or_else(|| {
+            let built: Vec<&str> = Self::ALL.iter().map(|backend| backend.name()).collect();
+            format!("backend `{name}` is not built in; available: {}", built.join(", "))
+        })
+    }
+
+    pub fn preferred() -> Result<Self, String> {
+        Self::ALL.first().copied().ok_or_else(|| {
+            "no window backend was built in; enable the eframe, softbuffer, minifb \
+             or pixels feature"
+                .to_string()
+        })
+    }
+
+    pub fn run(self) -> Result<(), String> {
+        match self {
+            #[cfg(feature = "eframe")]
+            Backend::Eframe => crate::app::run(),
+            #[cfg(feature = "minifb")]
+            Backend::Minifb => window::drive(minifb::MinifbWindow::new()?),
+            #[cfg(feature = "pixels")]
+            Backend::Pixels => window::drive(WinitWindow::<pixels::PixelsSurface>::new()?),
+            #[cfg(feature = "softbuffer")]
+            Backend::Softbuffer => {
+                window::drive(WinitWindow::<softbuffer::SoftbufferSurface>::new()?)
+            }
+        }
+    }
+}
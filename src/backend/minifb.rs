@@ -0,0 +1,111 @@
+//! minifb: a plain framebuffer window with no GPU requirements at all.
+//! It has no native drag support, so the window follows the mouse by hand.
+
+use std::time::{Duration, Instant};
+
+use ::minifb::{MouseButton, MouseMode, WindowOptions};
+use image::RgbaImage;
+
+use super::window::{pack_argb, Window};
+use crate::manifest::Interaction;
+use crate::{FRAME_H, FRAME_W};
+
+/// How long to sleep between input checks while waiting.
+const POLL_STEP_MS: u64 = 10;
+/// How far the mouse must move with the button down before it is a drag.
+const DRAG_THRESHOLD: f32 = 3.0;
+
+pub struct MinifbWindow {
+    window: ::minifb::Window,
+    buffer: Vec<u32>,
+    /// Where the button went down, in window coordinates.
+    pressed_at: Option<(f32, f32)>,
+    dragging: bool,
+}
+
+impl MinifbWindow {
+    pub fn new() -> Result<Self, String> {
+        let options = WindowOptions {
+            borderless: true,
+            transparency: true,
+            topmost: true,
+            resize: false,
+            ..WindowOptions::default()
+        };
+        let mut window =
+            ::minifb::Window::new("Tiny Clippy", FRAME_W as usize, FRAME_H as usize, options)
+                .map_err(|err| format!("failed to open a minifb window: {err}"))?;
+        // `poll` does its own pacing.
+        window.set_target_fps(0);
+
+        Ok(Self {
+            window,
+            buffer: vec![0; (FRAME_W * FRAME_H) as usize],
+            pressed_at: None,
+            dragging: false,
+        })
+    }
+
+    fn track_mouse(&mut self, interactions: &mut Vec<Interaction>) {
+        let down = self.window.get_mouse_down(MouseButton::Left);
+        let Some((x, y)) = self.window.get_mouse_pos(MouseMode::Pass) else {
+            return;
+        };
+
+        match (down, self.pressed_at) {
+            (true, None) => self.pressed_at = Some((x, y)),
+            (true, Some((start_x, start_y))) => {
+                let (dx, dy) = (x - start_x, y - start_y);
+                if !self.dragging && dx.hypot(dy) > DRAG_THRESHOLD {
+                    self.dragging = true;
+                    interactions.push(Interaction::DragStart);
+                }
+                if self.dragging {
+                    // The grab point stays under the cursor, so moving the
+                    // window by the offset keeps it there.
+                    let (window_x, window_y) = self.window.get_position();
+                    self.window
+                        .set_position(window_x + dx as isize, window_y + dy as isize);
+                }
+            }
+            (false, Some(_)) => {
+                interactions.push(if self.dragging {
+                    Interaction::DragEnd
+                } else {
+                    Interaction::Click
+                });
+                self.pressed_at = None;
+                self.dragging = false;
+            }
+            (false, None) => {}
+        }
+    }
+}
+
+impl Window for MinifbWindow {
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String> {
+        pack_argb(frame, &mut self.buffer);
+        self.window
+            .update_with_buffer(&self.buffer, FRAME_W as usize, FRAME_H as usize)
+            .map_err(|err| err.to_string())
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Option<Vec<Interaction>> {
+        let deadline = Instant::now() + timeout;
+        let mut interactions = Vec::new();
+
+        loop {
+            self.window.update();
+            if !self.window.is_open() {
+                return None;
+            }
+            self.track_mouse(&mut interactions);
+
+            let now = Instant::now();
+            if !interactions.is_empty() || now >= deadline {
+                return Some(interactions);
+            }
+            std::thread::sleep((deadline - now).min(Duration::from_millis(POLL_STEP_MS)));
+        }
+    }
+}
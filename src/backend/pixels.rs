@@ -0,0 +1,37 @@
+//! pixels: a wgpu texture scaled to the window, for GPUs without a good GL
+//! driver. Whether the window is see-through depends on the alpha modes the
+//! surface offers.
+
+use std::rc::Rc;
+
+use ::pixels::{Pixels, SurfaceTexture};
+use ::winit::window::Window;
+use image::RgbaImage;
+
+use super::winit::Surface;
+use crate::{FRAME_H, FRAME_W};
+
+pub struct PixelsSurface {
+    pixels: Pixels,
+}
+
+impl Surface for PixelsSurface {
+    fn new(window: Rc<Window>) -> Result<Self, String> {
+        let size = window.inner_size();
+        let texture = SurfaceTexture::new(size.width, size.height, &*window);
+        let mut pixels = Pixels::new(FRAME_W, FRAME_H, texture).map_err(|err| err.to_string())?;
+        pixels.clear_color(::pixels::wgpu::Color::TRANSPARENT);
+        Ok(Self { pixels })
+    }
+
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String> {
+        self.pixels.frame_mut().copy_from_slice(frame.as_raw());
+        self.pixels.render().map_err(|err| err.to_string())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            let _ = self.pixels.resize_surface(width, height);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! softbuffer: CPU pixels blitted straight to the window, no GL or GPU.
+
+use std::num::NonZeroU32;
+use std::rc::Rc;
+
+use ::winit::window::Window;
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+
+use super::window::pack_argb;
+use super::winit::Surface;
+
+pub struct SoftbufferSurface {
+    window: Rc<Window>,
+    surface: ::softbuffer::Surface<Rc<Window>, Rc<Window>>,
+}
+
+impl Surface for SoftbufferSurface {
+    fn new(window: Rc<Window>) -> Result<Self, String> {
+        let context = ::softbuffer::Context::new(window.clone()).map_err(|err| err.to_string())?;
+        let surface =
+            ::softbuffer::Surface::new(&context, window.clone()).map_err(|err| err.to_string())?;
+        Ok(Self { window, surface })
+    }
+
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String> {
+        let size = self.window.inner_size();
+        let (width, height) = (NonZeroU32::new(size.width), NonZeroU32::new(size.height));
+        let (Some(width), Some(height)) = (width, height) else {
+            // Minimized; nothing to draw into.
+            return Ok(());
+        };
+        self.surface.resize(width, height).map_err(|err| err.to_string())?;
+
+        // The buffer is in physical pixels, so HiDPI screens need the frame
+        // scaled up to match.
+        let scaled;
+        let frame = if frame.dimensions() == (size.width, size.height) {
+            frame
+        } else {
+            scaled = imageops::resize(frame, size.width, size.height, FilterType::Nearest);
+            &scaled
+        };
+
+        let mut buffer = self.surface.buffer_mut().map_err(|err| err.to_string())?;
+        pack_argb(frame, &mut buffer);
+        buffer.present().map_err(|err| err.to_string())
+    }
+}
@@ -0,0 +1,82 @@
+//! The loop shared by the bare-window backends.
+
+use std::time::{Duration, Instant};
+
+use image::{Rgba, RgbaImage};
+
+use crate::manifest::{ClipManifest, Interaction};
+use crate::personality::Personality;
+use crate::{extract_frame, load_sprite_sheet, Animation};
+
+/// Longest a backend may block waiting for input, so a closed window or a
+/// newly due clip never goes unnoticed for long.
+const MAX_WAIT_MS: u64 = 200;
+
+/// A bare window showing one FRAME_W x FRAME_H frame at a time.
+pub trait Window {
+    /// Replaces what the window shows with `frame`.
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String>;
+
+    /// Handles window events for at most `timeout`, returning any clicks
+    /// or drags that happened, or `None` once the window is closed.
+    fn poll(&mut self, timeout: Duration) -> Option<Vec<Interaction>>;
+}
+
+/// Plays the animation in `window` until it is closed.
+pub fn drive(mut window: impl Window) -> Result<(), String> {
+    let sprite_sheet = load_sprite_sheet();
+    let mut animation = Animation::new(ClipManifest::embedded(), Personality::Calm);
+    let mut shown = None;
+
+    loop {
+        let coords = animation.update();
+        let fading = animation.fading_frame();
+
+        if shown != Some((coords, fading)) {
+            let mut frame = extract_frame(&sprite_sheet, coords.0, coords.1);
+            if let Some(((from_x, from_y), alpha)) = fading {
+                blend_over(&mut frame, &extract_frame(&sprite_sheet, from_x, from_y), alpha);
+            }
+            window.present(&frame)?;
+            shown = Some((coords, fading));
+        }
+
+        let wait = animation.next_change().saturating_duration_since(Instant::now());
+        let Some(interactions) = window.poll(wait.min(Duration::from_millis(MAX_WAIT_MS)))
+        else {
+            return Ok(());
+        };
+        for interaction in interactions {
+            animation.react(interaction);
+        }
+    }
+}
+
+/// Draws `top` over `frame` at `opacity`, the CPU version of the crossfade
+/// the eframe app does with a tinted second image.
+fn blend_over(frame: &mut RgbaImage, top: &RgbaImage, opacity: f32) {
+    for (below, above) in frame.pixels_mut().zip(top.pixels()) {
+        let top_a = above[3] as f32 / 255.0 * opacity;
+        let below_a = below[3] as f32 / 255.0 * (1.0 - top_a);
+        let out_a = top_a + below_a;
+        if out_a <= 0.0 {
+            *below = Rgba([0, 0, 0, 0]);
+            continue;
+        }
+
+        let mix = |c: usize| ((above[c] as f32 * top_a + below[c] as f32 * below_a) / out_a) as u8;
+        *below = Rgba([mix(0), mix(1), mix(2), (out_a * 255.0) as u8]);
+    }
+}
+
+/// Packs a frame as premultiplied 0xAARRGGBB, the layout minifb and
+/// softbuffer expect. Platforms without window alpha show black instead of
+/// transparent pixels.
+#[cfg(any(feature = "minifb", feature = "softbuffer"))]
+pub fn pack_argb(frame: &RgbaImage, out: &mut [u32]) {
+    for (pixel, packed) in frame.pixels().zip(out.iter_mut()) {
+        let [r, g, b, a] = pixel.0;
+        let premultiply = |c: u8| c as u32 * a as u32 / 255;
+        *packed = (a as u32) << 24 | premultiply(r) << 16 | premultiply(g) << 8 | premultiply(b);
+    }
+}
@@ -0,0 +1,179 @@
+//! A winit window for the backends that only differ in how pixels reach
+//! the screen. Events are pumped on demand so the window fits the same
+//! pull-style loop as minifb.
+
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use ::winit::application::ApplicationHandler;
+use ::winit::dpi::{LogicalSize, PhysicalPosition};
+use ::winit::event::{ElementState, MouseButton, WindowEvent};
+use ::winit::event_loop::{ActiveEventLoop, EventLoop};
+use ::winit::platform::pump_events::{EventLoopExtPumpEvents, PumpStatus};
+use ::winit::window::{WindowAttributes, WindowId, WindowLevel};
+use image::RgbaImage;
+
+use super::window::Window;
+use crate::manifest::Interaction;
+use crate::{DRAG_SETTLE_MS, FRAME_H, FRAME_W};
+
+/// How far the mouse must move with the button down before it is a drag.
+const DRAG_THRESHOLD: f64 = 3.0;
+
+/// Gets frames onto a winit window.
+pub trait Surface: Sized {
+    fn new(window: Rc<::winit::window::Window>) -> Result<Self, String>;
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String>;
+    fn resize(&mut self, _width: u32, _height: u32) {}
+}
+
+pub struct WinitWindow<S> {
+    event_loop: EventLoop<()>,
+    state: State<S>,
+}
+
+struct State<S> {
+    // Declared before `window` so it is dropped first.
+    surface: Option<S>,
+    window: Option<Rc<::winit::window::Window>>,
+    error: Option<String>,
+    closed: bool,
+    /// The last frame presented, redrawn when the window is exposed.
+    frame: Option<RgbaImage>,
+    cursor: PhysicalPosition<f64>,
+    pressed_at: Option<PhysicalPosition<f64>>,
+    /// When the window last moved during a drag. The window manager owns
+    /// the mouse until the drag ends, so the release never arrives and the
+    /// end is guessed once moves stop.
+    drag_moved: Option<Instant>,
+    interactions: Vec<Interaction>,
+}
+
+impl<S: Surface> WinitWindow<S> {
+    pub fn new() -> Result<Self, String> {
+        let event_loop = EventLoop::new().map_err(|err| err.to_string())?;
+        let mut window = Self {
+            event_loop,
+            state: State {
+                surface: None,
+                window: None,
+                error: None,
+                closed: false,
+                frame: None,
+                cursor: PhysicalPosition::new(0.0, 0.0),
+                pressed_at: None,
+                drag_moved: None,
+                interactions: Vec::new(),
+            },
+        };
+
+        // The window can only be created once the loop has resumed.
+        while window.state.surface.is_none() {
+            window.event_loop.pump_app_events(Some(Duration::ZERO), &mut window.state);
+            if let Some(err) = window.state.error.take() {
+                return Err(err);
+            }
+        }
+        Ok(window)
+    }
+}
+
+impl<S: Surface> Window for WinitWindow<S> {
+    fn present(&mut self, frame: &RgbaImage) -> Result<(), String> {
+        let surface = self.state.surface.as_mut().ok_or("the window is gone")?;
+        surface.present(frame)?;
+        self.state.frame = Some(frame.clone());
+        Ok(())
+    }
+
+    fn poll(&mut self, timeout: Duration) -> Option<Vec<Interaction>> {
+        let status = self.event_loop.pump_app_events(Some(timeout), &mut self.state);
+        if matches!(status, PumpStatus::Exit(_)) || self.state.closed {
+            return None;
+        }
+
+        let settle = Duration::from_millis(DRAG_SETTLE_MS);
+        if self.state.drag_moved.is_some_and(|moved| moved.elapsed() >= settle) {
+            self.state.drag_moved = None;
+            self.state.interactions.push(Interaction::DragEnd);
+        }
+        Some(std::mem::take(&mut self.state.interactions))
+    }
+}
+
+impl<S: Surface> ApplicationHandler for State<S> {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.window.is_some() {
+            return;
+        }
+
+        let attributes = WindowAttributes::default()
+            .with_title("Tiny Clippy")
+            .with_decorations(false)
+            .with_transparent(true)
+            .with_resizable(false)
+            .with_window_level(WindowLevel::AlwaysOnTop)
+            .with_inner_size(LogicalSize::new(FRAME_W, FRAME_H));
+
+        let created = event_loop
+            .create_window(attributes)
+            .map_err(|err| err.to_string())
+            .and_then(|window| {
+                let window = Rc::new(window);
+                let surface = S::new(window.clone())?;
+                Ok((window, surface))
+            });
+        match created {
+            Ok((window, surface)) => {
+                self.window = Some(window);
+                self.surface = Some(surface);
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn window_event(&mut self, _: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            WindowEvent::CloseRequested => self.closed = true,
+            WindowEvent::Resized(size) => {
+                if let Some(surface) = &mut self.surface {
+                    surface.resize(size.width, size.height);
+                }
+            }
+            WindowEvent::RedrawRequested => {
+                if let (Some(surface), Some(frame)) = (&mut self.surface, &self.frame) {
+                    let _ = surface.present(frame);
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = position;
+                let Some(pressed_at) = self.pressed_at else {
+                    return;
+                };
+
+                let (dx, dy) = (position.x - pressed_at.x, position.y - pressed_at.y);
+                if dx.hypot(dy) > DRAG_THRESHOLD {
+                    self.pressed_at = None;
+                    if let Some(window) = &self.window {
+                        if window.drag_window().is_ok() {
+                            self.drag_moved = Some(Instant::now());
+                            self.interactions.push(Interaction::DragStart);
+                        }
+                    }
+                }
+            }
+            WindowEvent::Moved(_) if self.drag_moved.is_some() => {
+                self.drag_moved = Some(Instant::now());
+            }
+            WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => match state {
+                ElementState::Pressed => self.pressed_at = Some(self.cursor),
+                ElementState::Released => {
+                    if self.pressed_at.take().is_some() {
+                        self.interactions.push(Interaction::Click);
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,725 @@
+//! The speech balloon: word-wrapped Markdown in a pale yellow box beside
+//! Clippy, with a tail pointing at him, and optionally buttons, a list of
+//! choices or a text field that answer a question.
+
+use eframe::egui;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::markdown::{self, Document, FONT_SIZE};
+
+/// Widest the text gets before it wraps, in points.
+const MAX_TEXT_WIDTH: f32 = 200.0;
+/// Taller text scrolls inside the balloon.
+const MAX_TEXT_HEIGHT: f32 = 240.0;
+const PADDING: f32 = 8.0;
+const ROUNDING: f32 = 6.0;
+/// Gap between the balloon and the sprite, bridged by the tail.
+const TAIL_HEIGHT: f32 = 14.0;
+const TAIL_WIDTH: f32 = 14.0;
+const FILL: egui::Color32 = egui::Color32::from_rgb(255, 255, 204);
+const INK: egui::Color32 = egui::Color32::BLACK;
+/// Default time on screen: a base plus reading time per character.
+const BASE_TIMEOUT_MS: u64 = 3000;
+const PER_CHAR_MS: u64 = 60;
+/// Characters per second typed out when nothing else is asked for.
+pub const DEFAULT_REVEAL_RATE: f32 = 40.0;
+
+/// Space between the text and the choices under it.
+const CHOICE_GAP: f32 = 8.0;
+const BUTTON_PADDING: egui::Vec2 = egui::vec2(10.0, 3.0);
+const BUTTON_MIN_WIDTH: f32 = 48.0;
+const BUTTON_GAP: f32 = 6.0;
+const BUTTON_FILL: egui::Color32 = egui::Color32::from_rgb(240, 240, 240);
+const HOVER_FILL: egui::Color32 = egui::Color32::from_rgb(255, 238, 153);
+pub const MAX_BUTTONS: usize = 5;
+/// List rows: the bullet sits in the indent, the label wraps after it.
+const LIST_INDENT: f32 = 16.0;
+const LIST_ROW_PADDING: f32 = 2.0;
+const BULLET_RADIUS: f32 = 4.0;
+const BULLET: egui::Color32 = egui::Color32::from_rgb(0, 84, 227);
+/// Narrowest the text field gets, leaving room to type a sentence.
+const INPUT_WIDTH: f32 = 160.0;
+const INPUT_MARGIN: egui::Vec2 = egui::vec2(4.0, 2.0);
+const INPUT_FILL: egui::Color32 = egui::Color32::WHITE;
+const SUBMIT_LABEL: &str = "OK";
+
+/// Which side of Clippy the balloon sits on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Above,
+    Below,
+    Left,
+    Right,
+}
+
+impl Side {
+    /// In order of preference.
+    const ALL: [Side; 4] = [Side::Above, Side::Below, Side::Right, Side::Left];
+
+    /// The body on this side of `sprite`, slid along it to stay on `monitor`
+    /// as far as the tail can still reach him.
+    fn body(self, size: egui::Vec2, sprite: egui::Rect, monitor: Option<egui::Rect>) -> egui::Rect {
+        let center = sprite.center();
+        let mut min = match self {
+            Side::Above => egui::pos2(center.x - size.x / 2.0, sprite.top() - TAIL_HEIGHT - size.y),
+            Side::Below => egui::pos2(center.x - size.x / 2.0, sprite.bottom() + TAIL_HEIGHT),
+            Side::Left => egui::pos2(sprite.left() - TAIL_HEIGHT - size.x, center.y - size.y / 2.0),
+            Side::Right => egui::pos2(sprite.right() + TAIL_HEIGHT, center.y - size.y / 2.0),
+        };
+
+        if let Some(monitor) = monitor {
+            let inset = ROUNDING + TAIL_WIDTH / 2.0;
+            let slide = |start: f32, length: f32, screen: egui::Rangef, middle: f32| {
+                let on_screen = start.min(screen.max - length).max(screen.min);
+                on_screen.min(middle - inset).max(middle + inset - length)
+            };
+            match self {
+                Side::Above | Side::Below => {
+                    min.x = slide(min.x, size.x, monitor.x_range(), center.x);
+                }
+                Side::Left | Side::Right => {
+                    min.y = slide(min.y, size.y, monitor.y_range(), center.y);
+                }
+            }
+        }
+        egui::Rect::from_min_size(min, size)
+    }
+}
+
+/// Where a balloon goes around Clippy, in desktop points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub side: Side,
+    /// The body beside Clippy where he is now, before any nudge.
+    pub body: egui::Rect,
+    /// How far Clippy and the balloon move over together when it doesn't
+    /// fit beside him anywhere on the monitor.
+    pub nudge: egui::Vec2,
+}
+
+/// Places a balloon of `size` above, below, right or left of `sprite`,
+/// whichever comes first that fits on `monitor`. When none does, it goes
+/// where Clippy has to move the least to make room. Without a monitor to
+/// go by it goes above.
+pub fn place(size: egui::Vec2, sprite: egui::Rect, monitor: Option<egui::Rect>) -> Placement {
+    let Some(monitor) = monitor else {
+        let body = Side::Above.body(size, sprite, None);
+        return Placement { side: Side::Above, body, nudge: egui::Vec2::ZERO };
+    };
+
+    Side::ALL
+        .into_iter()
+        .map(|side| {
+            let body = side.body(size, sprite, Some(monitor));
+            let nudge = nudge_into(body.union(sprite), monitor);
+            Placement { side, body, nudge }
+        })
+        .min_by(|a, b| a.nudge.length().total_cmp(&b.nudge.length()))
+        .expect("there is always a side to try")
+}
+
+/// How far `rect` has to move to be on `monitor`, or to line up with its
+/// top-left corner when it is too big for it.
+fn nudge_into(rect: egui::Rect, monitor: egui::Rect) -> egui::Vec2 {
+    let axis = |range: egui::Rangef, screen: egui::Rangef| {
+        if range.min < screen.min || range.span() > screen.span() {
+            screen.min - range.min
+        } else if range.max > screen.max {
+            screen.max - range.max
+        } else {
+            0.0
+        }
+    };
+    egui::vec2(
+        axis(rect.x_range(), monitor.x_range()),
+        axis(rect.y_range(), monitor.y_range()),
+    )
+}
+
+/// What a question offers to answer it with.
+pub enum Choices {
+    None,
+    /// A row of buttons under the text, right-aligned.
+    Buttons(Vec<String>),
+    /// One option per line, for longer labels or more than fit as buttons.
+    List(Vec<String>),
+    /// A single-line text field showing a hint while empty, answered with
+    /// Enter or the button next to it.
+    Input(String),
+}
+
+impl Choices {
+    fn labels(&self) -> &[String] {
+        match self {
+            Choices::None | Choices::Input(_) => &[],
+            Choices::Buttons(labels) | Choices::List(labels) => labels,
+        }
+    }
+}
+
+/// How a balloon went away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Chosen(String),
+    /// Typed into a text field.
+    Submitted(String),
+    /// Nobody answered in time. Carries the default choice, if there is one.
+    TimedOut(Option<String>),
+    /// Clicked away, or Clippy was closed while it was up.
+    Dismissed,
+}
+
+impl Outcome {
+    /// The choice or text that answered the balloon, if anything did.
+    pub fn answer(&self) -> Option<&str> {
+        match self {
+            Outcome::Chosen(answer)
+            | Outcome::Submitted(answer)
+            | Outcome::TimedOut(Some(answer)) => Some(answer),
+            Outcome::TimedOut(None) | Outcome::Dismissed => None,
+        }
+    }
+
+    /// Short name for how the balloon went away, for hooks and the history.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Outcome::Chosen(_) => "chosen",
+            Outcome::Submitted(_) => "submitted",
+            Outcome::TimedOut(_) => "timeout",
+            Outcome::Dismissed => "dismissed",
+        }
+    }
+}
+
+/// Told how the balloon went away, exactly once.
+pub type Reply = Box<dyn FnOnce(Outcome) + Send>;
+
+pub struct Balloon {
+    /// The Markdown as given, for the history.
+    text: String,
+    /// Who asked for the balloon, for the history.
+    source: String,
+    document: Document,
+    shown_at: Instant,
+    /// `None` keeps the balloon up until it is clicked or answered. Counts
+    /// from when the whole text is on screen.
+    timeout: Option<Duration>,
+    /// Characters per second the text is typed out at; `None` shows it all
+    /// at once.
+    reveal_rate: Option<f32>,
+    /// Set when a click skipped the rest of the typing.
+    skipped_at: Option<Instant>,
+    choices: Choices,
+    /// Index into the choices taken when the balloon times out.
+    default: Option<usize>,
+    /// What has been typed into a text field so far.
+    typed: String,
+    /// Whether the text field has been given keyboard focus yet.
+    focused: bool,
+    reply: Option<Reply>,
+}
+
+/// Positions relative to the balloon body's top-left corner.
+struct Arrangement {
+    text: markdown::Layout,
+    /// Room the text gets; anything below scrolls.
+    text_height: f32,
+    choices: Vec<(Arc<egui::Galley>, egui::Rect)>,
+    input: Option<egui::Rect>,
+    size: egui::Vec2,
+}
+
+impl Balloon {
+    pub fn new(text: String, timeout: Option<Duration>) -> Self {
+        Self {
+            document: Document::parse(&text),
+            text,
+            source: String::new(),
+            shown_at: Instant::now(),
+            timeout,
+            reveal_rate: None,
+            skipped_at: None,
+            choices: Choices::None,
+            default: None,
+            typed: String::new(),
+            focused: false,
+            reply: None,
+        }
+    }
+
+    /// Turns the balloon into a question. `default` indexes into `choices`
+    /// and is what a timeout answers with.
+    pub fn with_choices(mut self, choices: Choices, default: Option<usize>) -> Self {
+        self.default = default.filter(|&index| index < choices.labels().len());
+        self.choices = choices;
+        self
+    }
+
+    /// Types the text out at `chars_per_sec` instead of showing it at once.
+    /// Zero or less turns that off.
+    pub fn with_reveal(mut self, chars_per_sec: f32) -> Self {
+        let typed = chars_per_sec > 0.0 && chars_per_sec.is_finite();
+        self.reveal_rate = typed.then_some(chars_per_sec);
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn shown_at(&self) -> Instant {
+        self.shown_at
+    }
+
+    /// Whether the balloon wants the keyboard.
+    pub fn has_input(&self) -> bool {
+        matches!(self.choices, Choices::Input(_))
+    }
+
+    pub fn with_reply(mut self, reply: Reply) -> Self {
+        self.reply = Some(reply);
+        self
+    }
+
+    /// Roughly how long it takes to read `text`.
+    pub fn reading_time(text: &str) -> Duration {
+        let chars = text.chars().count() as u64;
+        Duration::from_millis(BASE_TIMEOUT_MS + chars * PER_CHAR_MS)
+    }
+
+    /// When the whole text is, or will be, on screen.
+    fn revealed_at(&self) -> Instant {
+        let typed = match self.reveal_rate {
+            Some(rate) => {
+                let chars = self.document.char_count() as f32;
+                self.shown_at + Duration::from_secs_f32(chars / rate)
+            }
+            None => self.shown_at,
+        };
+        self.skipped_at.map_or(typed, |skipped| skipped.min(typed))
+    }
+
+    /// Whether the text is still being typed out.
+    pub fn revealing(&self) -> bool {
+        Instant::now() < self.revealed_at()
+    }
+
+    /// How many characters of the text are on screen.
+    fn shown_chars(&self) -> usize {
+        let chars = self.document.char_count();
+        match self.reveal_rate {
+            Some(rate) if self.revealing() => {
+                let typed = self.shown_at.elapsed().as_secs_f32() * rate;
+                (typed as usize).min(chars)
+            }
+            _ => chars,
+        }
+    }
+
+    /// When the next character appears, while the text is being typed out.
+    pub fn next_reveal(&self) -> Option<Instant> {
+        let rate = self.reveal_rate.filter(|_| self.revealing())?;
+        let next = (self.shown_chars() + 1) as f32 / rate;
+        Some(self.shown_at + Duration::from_secs_f32(next))
+    }
+
+    pub fn expires_at(&self) -> Option<Instant> {
+        self.timeout.map(|timeout| self.revealed_at() + timeout)
+    }
+
+    /// The outcome to close with once the timeout has run out.
+    pub fn timed_out(&self) -> Option<Outcome> {
+        let expired = self.expires_at().is_some_and(|at| Instant::now() >= at);
+        let default = self.default.map(|index| self.choices.labels()[index].clone());
+        expired.then_some(Outcome::TimedOut(default))
+    }
+
+    /// Reports `outcome` to whoever asked.
+    pub fn finish(mut self, outcome: Outcome) {
+        if let Some(reply) = self.reply.take() {
+            reply(outcome);
+        }
+    }
+
+    fn galley(ctx: &egui::Context, text: &str, wrap_width: f32) -> Arc<egui::Galley> {
+        let font = egui::FontId::proportional(FONT_SIZE);
+        // Colored when painted, so fading doesn't lay the text out again.
+        let color = egui::Color32::PLACEHOLDER;
+        ctx.fonts(|fonts| fonts.layout(text.to_string(), font, color, wrap_width))
+    }
+
+    fn arrange(&self, ctx: &egui::Context, opacity: f32) -> Arrangement {
+        let ink = INK.gamma_multiply(opacity);
+        let text = self.document.layout(ctx, MAX_TEXT_WIDTH, self.shown_chars(), ink, opacity);
+        let text_height = text.size.y.min(MAX_TEXT_HEIGHT);
+        let mut inner = egui::vec2(text.size.x, text_height);
+        let top = PADDING + inner.y + CHOICE_GAP;
+        let mut input = None;
+
+        let choices = match &self.choices {
+            Choices::None => Vec::new(),
+            Choices::Buttons(labels) => {
+                let galleys: Vec<_> =
+                    labels.iter().map(|label| Self::galley(ctx, label, f32::INFINITY)).collect();
+                let widths: Vec<f32> = galleys
+                    .iter()
+                    .map(|galley| (galley.size().x + BUTTON_PADDING.x * 2.0).max(BUTTON_MIN_WIDTH))
+                    .collect();
+                let height = galleys.iter().map(|galley| galley.size().y).fold(0.0, f32::max)
+                    + BUTTON_PADDING.y * 2.0;
+                let row = widths.iter().sum::<f32>() + BUTTON_GAP * (widths.len() - 1) as f32;
+                inner = egui::vec2(inner.x.max(row), inner.y + CHOICE_GAP + height);
+
+                let mut x = PADDING + inner.x - row;
+                galleys
+                    .into_iter()
+                    .zip(widths)
+                    .map(|(galley, width)| {
+                        let rect = egui::Rect::from_min_size(
+                            egui::pos2(x, top),
+                            egui::vec2(width, height),
+                        );
+                        x += width + BUTTON_GAP;
+                        (galley, rect)
+                    })
+                    .collect()
+            }
+            Choices::List(labels) => {
+                let galleys: Vec<_> = labels
+                    .iter()
+                    .map(|label| Self::galley(ctx, label, MAX_TEXT_WIDTH - LIST_INDENT))
+                    .collect();
+                let rows = galleys.iter().map(|galley| galley.size().x + LIST_INDENT);
+                inner.x = rows.fold(inner.x, f32::max);
+
+                let mut y = top;
+                galleys
+                    .into_iter()
+                    .map(|galley| {
+                        let height = galley.size().y + LIST_ROW_PADDING * 2.0;
+                        let rect = egui::Rect::from_min_size(
+                            egui::pos2(PADDING, y),
+                            egui::vec2(inner.x, height),
+                        );
+                        y += height;
+                        (galley, rect)
+                    })
+                    .collect()
+            }
+            Choices::Input(_) => {
+                let galley = Self::galley(ctx, SUBMIT_LABEL, f32::INFINITY);
+                let button = egui::vec2(
+                    (galley.size().x + BUTTON_PADDING.x * 2.0).max(BUTTON_MIN_WIDTH),
+                    galley.size().y + BUTTON_PADDING.y * 2.0,
+                );
+                inner.x = inner.x.max(INPUT_WIDTH + BUTTON_GAP + button.x);
+
+                let field_width = inner.x - BUTTON_GAP - button.x;
+                input = Some(egui::Rect::from_min_size(
+                    egui::pos2(PADDING, top),
+                    egui::vec2(field_width, button.y),
+                ));
+                let button_min = egui::pos2(PADDING + field_width + BUTTON_GAP, top);
+                vec![(galley, egui::Rect::from_min_size(button_min, button))]
+            }
+        };
+        if let Some((_, last)) = choices.last() {
+            inner.y = last.bottom() - PADDING;
+        }
+
+        Arrangement {
+            text,
+            text_height,
+            choices,
+            input,
+            size: inner + egui::Vec2::splat(PADDING * 2.0),
+        }
+    }
+
+    /// Size of the balloon body in points, not counting the tail.
+    pub fn size(&self, ctx: &egui::Context) -> egui::Vec2 {
+        self.arrange(ctx, 1.0).size
+    }
+
+    /// Draws the balloon in `body` on the `side` of `sprite` with its tail
+    /// reaching over to him, and returns an outcome once the balloon has been
+    /// answered or, without choices, clicked. Clicking while the text is
+    /// still being typed out shows the rest of it instead.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        body: egui::Rect,
+        sprite: egui::Rect,
+        side: Side,
+        opacity: f32,
+    ) -> Option<Outcome> {
+        let arrangement = self.arrange(ui.ctx(), opacity);
+        let fill = FILL.gamma_multiply(opacity);
+        let ink = INK.gamma_multiply(opacity);
+        let stroke = egui::Stroke::new(1.0, ink);
+        let painter = ui.painter().clone();
+
+        painter.rect(body, ROUNDING, fill, stroke);
+
+        // The tail leaves the edge facing Clippy as close to his middle as
+        // the rounded corners allow, then slants to its tip.
+        let inset = ROUNDING + TAIL_WIDTH / 2.0;
+        let half = TAIL_WIDTH / 2.0;
+        let center = sprite.center();
+        let (base_a, base_b, tip, towards) = match side {
+            Side::Above | Side::Below => {
+                let x = center.x.min(body.right() - inset).max(body.left() + inset);
+                let (edge, tip_y, towards) = match side {
+                    Side::Above => (body.bottom(), sprite.top(), egui::Vec2::DOWN),
+                    _ => (body.top(), sprite.bottom(), egui::Vec2::UP),
+                };
+                let tip = egui::pos2(center.x, tip_y);
+                (egui::pos2(x - half, edge), egui::pos2(x + half, edge), tip, towards)
+            }
+            Side::Left | Side::Right => {
+                let y = center.y.min(body.bottom() - inset).max(body.top() + inset);
+                let (edge, tip_x, towards) = match side {
+                    Side::Left => (body.right(), sprite.left(), egui::Vec2::RIGHT),
+                    _ => (body.left(), sprite.right(), egui::Vec2::LEFT),
+                };
+                let tip = egui::pos2(tip_x, center.y);
+                (egui::pos2(edge, y - half), egui::pos2(edge, y + half), tip, towards)
+            }
+        };
+        // Filled slightly into the body to cover the border under the tail.
+        let overlap = towards * stroke.width;
+        painter.add(egui::Shape::convex_polygon(
+            vec![base_a - overlap, base_b - overlap, tip],
+            fill,
+            egui::Stroke::NONE,
+        ));
+        painter.line_segment([base_a, tip], stroke);
+        painter.line_segment([base_b, tip], stroke);
+
+        // Before the text and choices, so links and buttons sit on top of it
+        // and get their own clicks.
+        let body_id = ui.id().with("balloon");
+        let body_clicked = ui.interact(body, body_id, egui::Sense::click()).clicked();
+
+        let text_rect = egui::Rect::from_min_size(
+            body.min + egui::Vec2::splat(PADDING),
+            egui::vec2(arrangement.text.size.x, arrangement.text_height),
+        );
+        let mut text_ui = ui.new_child(egui::UiBuilder::new().max_rect(text_rect));
+        let link = egui::ScrollArea::vertical()
+            .id_salt("balloon-text")
+            .max_height(arrangement.text_height)
+            .auto_shrink(false)
+            .show(&mut text_ui, |ui| {
+                let (rect, _) =
+                    ui.allocate_exact_size(arrangement.text.size, egui::Sense::hover());
+                arrangement.text.show(ui, rect.min)
+            })
+            .inner;
+        if let Some(url) = link {
+            ui.ctx().open_url(egui::OpenUrl::new_tab(url));
+        }
+
+        let labels = self.choices.labels();
+        let mut outcome = None;
+        let mut submit = false;
+        for (index, (galley, rect)) in arrangement.choices.into_iter().enumerate() {
+            let rect = rect.translate(body.min.to_vec2());
+            let id = ui.id().with(("balloon-choice", index));
+            let response = ui.interact(rect, id, egui::Sense::click());
+            let hovered = response.hovered();
+
+            match self.choices {
+                Choices::Buttons(_) | Choices::Input(_) => {
+                    let button = if hovered { HOVER_FILL } else { BUTTON_FILL };
+                    painter.rect(rect, 3.0, button.gamma_multiply(opacity), stroke);
+                    painter.galley(rect.center() - galley.size() / 2.0, galley, ink);
+                }
+                _ => {
+                    if hovered {
+                        painter.rect_filled(rect, 3.0, HOVER_FILL.gamma_multiply(opacity));
+                    }
+                    let first_line = galley.rows.first().map_or(FONT_SIZE, |row| row.height());
+                    let bullet = egui::pos2(
+                        rect.left() + LIST_INDENT / 2.0,
+                        rect.top() + LIST_ROW_PADDING + first_line / 2.0,
+                    );
+                    painter.circle_filled(bullet, BULLET_RADIUS, BULLET.gamma_multiply(opacity));
+                    let text_pos = rect.min + egui::vec2(LIST_INDENT, LIST_ROW_PADDING);
+                    painter.galley(text_pos, galley, ink);
+                }
+            }
+
+            if response.clicked() {
+                match self.choices {
+                    Choices::Input(_) => submit = true,
+                    _ => outcome = Some(Outcome::Chosen(labels[index].clone())),
+                }
+            }
+        }
+
+        if let (Choices::Input(hint), Some(field)) = (&self.choices, arrangement.input) {
+            let field = field.translate(body.min.to_vec2());
+            painter.rect(field, 2.0, INPUT_FILL.gamma_multiply(opacity), stroke);
+
+            let edit = egui::TextEdit::singleline(&mut self.typed)
+                .hint_text(hint.as_str())
+                .font(egui::FontId::proportional(FONT_SIZE))
+                .text_color(ink)
+                .frame(false)
+                .margin(egui::Margin::symmetric(INPUT_MARGIN.x, INPUT_MARGIN.y))
+                .desired_width(field.width() - INPUT_MARGIN.x * 2.0);
+            let response = ui
+                .scope(|ui| {
+                    // The dark theme's cursor would vanish against the white field.
+                    ui.visuals_mut().text_cursor.stroke.color = INK;
+                    ui.put(field, edit)
+                })
+                .inner;
+
+            if !self.focused {
+                response.request_focus();
+                self.focused = true;
+            }
+            if response.lost_focus() {
+                submit |= ui.input(|i| i.key_pressed(egui::Key::Enter));
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    outcome = Some(Outcome::Dismissed);
+                }
+            }
+            if submit {
+                let text = self.typed.trim();
+                if text.is_empty() {
+                    response.request_focus();
+                } else {
+                    outcome = Some(Outcome::Submitted(text.to_string()));
+                }
+            }
+        }
+
+        if body_clicked && self.revealing() {
+            self.skipped_at = Some(Instant::now());
+        } else if body_clicked && matches!(self.choices, Choices::None) {
+            outcome = Some(Outcome::Dismissed);
+        }
+        outcome
+    }
+}
+
+impl Drop for Balloon {
+    /// A balloon that goes away unanswered, such as when Clippy is closed
+    /// under it, still tells whoever is waiting.
+    fn drop(&mut self) {
+        if let Some(reply) = self.reply.take() {
+            reply(Outcome::Dismissed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: egui::Rect =
+        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1920.0, 1080.0));
+    const SPRITE: egui::Vec2 = egui::vec2(124.0, 93.0);
+    const BALLOON: egui::Vec2 = egui::vec2(220.0, 120.0);
+
+    /// Clippy with his top-left corner at `x`, `y`.
+    fn sprite(x: f32, y: f32) -> egui::Rect {
+        egui::Rect::from_min_size(egui::pos2(x, y), SPRITE)
+    }
+
+    /// Clippy tucked into the monitor at a fraction of the way across and down.
+    fn sprite_at(across: f32, down: f32) -> egui::Rect {
+        let room = MONITOR.size() - SPRITE;
+        sprite(room.x * across, room.y * down)
+    }
+
+    #[test]
+    fn sides_go_in_order_of_preference() {
+        assert_eq!(place(BALLOON, sprite_at(0.5, 0.5), Some(MONITOR)).side, Side::Above);
+        assert_eq!(place(BALLOON, sprite_at(0.5, 0.0), Some(MONITOR)).side, Side::Below);
+
+        // Too short a monitor for the balloon above or below him.
+        let strip = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1920.0, 130.0));
+        assert_eq!(place(BALLOON, sprite(900.0, 20.0), Some(strip)).side, Side::Right);
+        assert_eq!(place(BALLOON, sprite(1790.0, 20.0), Some(strip)).side, Side::Left);
+    }
+
+    #[test]
+    fn fits_at_every_edge_and_corner() {
+        for across in [0.0, 0.5, 1.0] {
+            for down in [0.0, 0.5, 1.0] {
+                let sprite = sprite_at(across, down);
+                let placement = place(BALLOON, sprite, Some(MONITOR));
+                let at = format!("{across}, {down}: {placement:?}");
+
+                assert_eq!(placement.nudge, egui::Vec2::ZERO, "{at}");
+                assert!(MONITOR.contains_rect(placement.body), "{at}");
+                assert!(!placement.body.intersects(sprite.shrink(0.5)), "{at}");
+                // The tail still has a stretch of straight edge to sit on.
+                let inset = ROUNDING + TAIL_WIDTH / 2.0;
+                let center = sprite.center();
+                match placement.side {
+                    Side::Above | Side::Below => {
+                        let reach = placement.body.x_range().shrink(inset);
+                        assert!(reach.contains(center.x), "{at}");
+                    }
+                    Side::Left | Side::Right => {
+                        let reach = placement.body.y_range().shrink(inset);
+                        assert!(reach.contains(center.y), "{at}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn nudges_when_no_side_fits() {
+        // Clippy in the middle, with less room than the balloon on every side.
+        let monitor = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(400.0, 300.0));
+        let (size, sprite) = (egui::vec2(200.0, 200.0), sprite(138.0, 103.0));
+        let placement = place(size, sprite, Some(monitor));
+        assert_ne!(placement.nudge, egui::Vec2::ZERO);
+        let moved = placement.body.union(sprite).translate(placement.nudge);
+        assert!(monitor.contains_rect(moved), "{placement:?}");
+    }
+
+    #[test]
+    fn balloon_larger_than_the_monitor_lines_up_with_its_corner() {
+        let size = MONITOR.size() + egui::vec2(100.0, 100.0);
+        let sprite = sprite_at(0.5, 0.5);
+        let placement = place(size, sprite, Some(MONITOR));
+        let moved = placement.body.union(sprite).translate(placement.nudge);
+        assert_eq!(moved.min, MONITOR.min);
+    }
+
+    #[test]
+    fn nudge_into_moves_the_least_onto_the_monitor() {
+        let inside = egui::Rect::from_min_size(egui::pos2(100.0, 100.0), BALLOON);
+        assert_eq!(nudge_into(inside, MONITOR), egui::Vec2::ZERO);
+        let off_top_left = inside.translate(egui::vec2(-150.0, -130.0));
+        assert_eq!(nudge_into(off_top_left, MONITOR), egui::vec2(50.0, 30.0));
+        let off_bottom_right = inside.translate(egui::vec2(1700.0, 900.0));
+        assert_eq!(nudge_into(off_bottom_right, MONITOR), egui::vec2(-100.0, -40.0));
+    }
+
+    #[test]
+    fn goes_above_without_a_monitor() {
+        let placement = place(BALLOON, sprite(0.0, 0.0), None);
+        assert_eq!(placement.side, Side::Above);
+        assert_eq!(placement.nudge, egui::Vec2::ZERO);
+        assert!(placement.body.max.y <= 0.0);
+    }
+}
@@ -0,0 +1,348 @@
+//! The control socket: a Unix domain socket in `$XDG_RUNTIME_DIR` for
+//! scripts that want to drive the Clippy already on the desktop. It takes
+//! one JSON command per line and answers each with one JSON line, carrying
+//! back the request's `id` if it had one:
+//!
+//! ```text
+//! {"id": 1, "cmd": "say", "text": "Build finished"}
+//! {"id": 1, "ok": true}
+//! ```
+//!
+//! Every connection also gets `{"event": ...}` lines as things happen, such
+//! as a balloon being answered or Clippy being clicked. Each connection has
+//! its own thread doing the writing, so a slow client never holds up the
+//! window.
+
+use eframe::egui;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[cfg(unix)]
+const SOCKET_NAME: &str = "tiny-clippy.sock";
+/// A client that stops reading is dropped rather than left to pile up lines.
+#[cfg(unix)]
+const WRITE_TIMEOUT_MS: u64 = 200;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// Plays a clip from `clips.toml` by name, cutting the current one short.
+    Play { clip: String },
+    /// Options as for `tiny-clippy say`; `timeout` is in seconds.
+    Say {
+        text: String,
+        timeout: Option<f32>,
+        rate: Option<f32>,
+        source: Option<String>,
+    },
+    /// Answered once the question is, with its `outcome` and `answer`.
+    Ask {
+        text: String,
+        choices: Vec<String>,
+        default: Option<String>,
+        timeout: Option<f32>,
+        #[serde(default)]
+        list: bool,
+        rate: Option<f32>,
+        source: Option<String>,
+    },
+    /// Puts Clippy's top-left corner at `x`, `y` in desktop points.
+    Move { x: f32, y: f32 },
+    Hide,
+    Show,
+    State,
+    Quit,
+}
+
+/// Lines for one client, shared by its replies and the events. The `Arc`
+/// tells connections apart.
+type Connection = Arc<Sender<String>>;
+
+pub struct Request {
+    pub command: Command,
+    pub reply: Reply,
+}
+
+/// Where the answer to one request goes. Can be kept and answered later,
+/// from any thread.
+pub struct Reply {
+    connection: Connection,
+    id: Value,
+}
+
+impl Reply {
+    /// Answers with `ok: true` and the fields of `body`, which should be an
+    /// object.
+    pub fn ok(self, body: Value) {
+        let mut message = json!({ "id": self.id, "ok": true });
+        extend(&mut message, body);
+        let _ = send(&self.connection, &message);
+    }
+
+    pub fn error(self, error: impl Display) {
+        let message = json!({ "id": self.id, "ok": false, "error": error.to_string() });
+        let _ = send(&self.connection, &message);
+    }
+}
+
+pub struct ControlServer {
+    path: PathBuf,
+    requests: Receiver<Request>,
+    connections: Arc<Mutex<Vec<Connection>>>,
+}
+
+impl ControlServer {
+    /// Listens on `$XDG_RUNTIME_DIR/tiny-clippy.sock`, waking `ctx` for
+    /// every request that comes in. Fails if another Clippy is already
+    /// listening there.
+    #[cfg(unix)]
+    pub fn start(ctx: &egui::Context) -> Result<Self, String> {
+        use std::fs;
+        use std::os::unix::fs::DirBuilderExt;
+        use std::os::unix::net::UnixStream;
+
+        let dir = std::env::var_os("XDG_RUNTIME_DIR").ok_or("XDG_RUNTIME_DIR is not set")?;
+        let path = PathBuf::from(dir).join(SOCKET_NAME);
+        if UnixStream::connect(&path).is_ok() {
+            return Err(format!("another Clippy is listening on {}", path.display()));
+        }
+        // Left behind by a Clippy that didn't get to clean up.
+        let _ = fs::remove_file(&path);
+
+        // The socket is bound in a directory only we can enter and made
+        // owner-only there before it is renamed into place, so nobody else
+        // can connect in between. Changing the umask instead would affect
+        // every thread.
+        let staging = path.with_extension(format!("{}.tmp", std::process::id()));
+        let _ = fs::remove_dir_all(&staging);
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&staging)
+            .map_err(|err| format!("failed to create {}: {err}", staging.display()))?;
+        let bound = bind_private(&staging.join(SOCKET_NAME), &path);
+        let _ = fs::remove_dir_all(&staging);
+        let listener = bound?;
+
+        let (sender, requests) = std::sync::mpsc::channel();
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let accepted = connections.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (sender, connections, ctx) = (sender.clone(), accepted.clone(), ctx.clone());
+                std::thread::spawn(move || serve(stream, sender, connections, ctx));
+            }
+        });
+
+        Ok(Self { path, requests, connections })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_ctx: &egui::Context) -> Result<Self, String> {
+        Err("the control socket needs Unix domain sockets".to_string())
+    }
+
+    /// Requests that came in since the last call, oldest first.
+    pub fn requests(&self) -> impl Iterator<Item = Request> + '_ {
+        self.requests.try_iter()
+    }
+
+    /// Tells every client about `event`, with the fields of `body`.
+    pub fn notify(&self, event: &str, body: Value) {
+        let mut message = json!({ "event": event });
+        extend(&mut message, body);
+        self.connections
+            .lock()
+            .unwrap()
+            .retain(|connection| send(connection, &message).is_ok());
+    }
+}
+
+/// Binds `staging`, makes it owner-only and moves it to `path`.
+#[cfg(unix)]
+fn bind_private(
+    staging: &std::path::Path,
+    path: &std::path::Path,
+) -> Result<std::os::unix::net::UnixListener, String> {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    let listener = std::os::unix::net::UnixListener::bind(staging)
+        .map_err(|err| format!("failed to listen on {}: {err}", staging.display()))?;
+    fs::set_permissions(staging, fs::Permissions::from_mode(0o600))
+        .map_err(|err| format!("failed to restrict {}: {err}", staging.display()))?;
+    fs::rename(staging, path)
+        .map_err(|err| format!("failed to move the socket to {}: {err}", path.display()))?;
+    Ok(listener)
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Reads requests from one client until it hangs up.
+#[cfg(unix)]
+fn serve(
+    stream: std::os::unix::net::UnixStream,
+    requests: std::sync::mpsc::Sender<Request>,
+    connections: Arc<Mutex<Vec<Connection>>>,
+    ctx: egui::Context,
+) {
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+
+    let Ok(writer) = stream.try_clone() else {
+        return;
+    };
+    let _ = writer.set_write_timeout(Some(Duration::from_millis(WRITE_TIMEOUT_MS)));
+    let (lines, outgoing) = std::sync::mpsc::channel();
+    std::thread::spawn(move || write_lines(writer, outgoing));
+    let connection: Connection = Arc::new(lines);
+    connections.lock().unwrap().push(connection.clone());
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (id, command) = parse(&line);
+        let reply = Reply { connection: connection.clone(), id };
+        match command {
+            Ok(command) => {
+                if requests.send(Request { command, reply }).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+            Err(err) => reply.error(err),
+        }
+    }
+
+    connections.lock().unwrap().retain(|other| !Arc::ptr_eq(other, &connection));
+}
+
+/// Writes `outgoing` to one client until it hangs up or stops reading, then
+/// shuts the socket so its reader stops too.
+#[cfg(unix)]
+fn write_lines(mut writer: std::os::unix::net::UnixStream, outgoing: Receiver<String>) {
+    use std::io::Write;
+
+    for line in outgoing {
+        if writeln!(writer, "{line}").and_then(|()| writer.flush()).is_err() {
+            break;
+        }
+    }
+    let _ = writer.shutdown(std::net::Shutdown::Both);
+}
+
+/// The request's `id`, kept even when the rest of it doesn't parse, and
+/// its command.
+fn parse(line: &str) -> (Value, Result<Command, String>) {
+    let mut value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(err) => return (Value::Null, Err(err.to_string())),
+    };
+    let id = value.as_object_mut().and_then(|object| object.remove("id"));
+    let command = Command::deserialize(value).map_err(|err| err.to_string());
+    (id.unwrap_or(Value::Null), command)
+}
+
+fn extend(message: &mut Value, body: Value) {
+    if let (Value::Object(message), Value::Object(body)) = (message, body) {
+        message.extend(body);
+    }
+}
+
+/// Queues `message` for the connection's writer; fails once it has gone.
+fn send(connection: &Connection, message: &Value) -> Result<(), String> {
+    connection.send(message.to_string()).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(line: &str) -> (Value, String) {
+        let (id, command) = parse(line);
+        (id, command.expect_err("parsed"))
+    }
+
+    #[test]
+    fn parses_commands_and_keeps_ids() {
+        let (id, command) = parse(r#"{"id": "a", "cmd": "say", "text": "Hi"}"#);
+        assert_eq!(id, json!("a"));
+        assert!(matches!(command, Ok(Command::Say { text, timeout: None, .. }) if text == "Hi"));
+        let (id, command) = parse(r#"{"cmd": "move", "x": 1, "y": 2.5}"#);
+        assert_eq!(id, Value::Null);
+        assert!(matches!(command, Ok(Command::Move { x: 1.0, y: 2.5 })));
+    }
+
+    #[test]
+    fn malformed_json_is_an_error_without_an_id() {
+        for line in [r#"{"id": 1, "cmd": "say""#, "say hello", "{}}"] {
+            assert_eq!(error(line).0, Value::Null, "{line}");
+        }
+    }
+
+    #[test]
+    fn bad_commands_keep_their_id() {
+        let (id, err) = error(r#"{"id": 7, "cmd": "dance"}"#);
+        assert_eq!(id, json!(7));
+        assert!(err.contains("dance"), "{err}");
+        let (id, err) = error(r#"{"id": 8, "cmd": "say"}"#);
+        assert_eq!(id, json!(8));
+        assert!(err.contains("text"), "{err}");
+        assert_eq!(error(r#"{"id": 9}"#).0, json!(9));
+        assert_eq!(error(r#"[1, 2]"#).0, Value::Null);
+        assert!(error(r#"{"cmd": "move", "x": "left", "y": 0}"#).1.contains("invalid type"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn serves_a_private_socket() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixStream;
+
+        let dir = std::env::temp_dir().join(format!("tiny-clippy-control-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::env::set_var("XDG_RUNTIME_DIR", &dir);
+        let server = ControlServer::start(&egui::Context::default()).unwrap();
+        let path = dir.join(SOCKET_NAME);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "staging left behind");
+        assert!(ControlServer::start(&egui::Context::default()).is_err());
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, r#"{{"id": 1, "cmd": "state"}}"#).unwrap();
+        let mut lines = BufReader::new(client.try_clone().unwrap()).lines();
+        let request = loop {
+            if let Some(request) = server.requests().next() {
+                break request;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        };
+        assert!(matches!(request.command, Command::State));
+        request.reply.ok(json!({ "hidden": false }));
+        let reply: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(reply, json!({ "id": 1, "ok": true, "hidden": false }));
+
+        server.notify("clicked", json!({}));
+        let event: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(event, json!({ "event": "clicked" }));
+
+        drop(server);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
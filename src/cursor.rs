@@ -0,0 +1,110 @@
+//! Where the mouse is on the desktop, even when it isn't over our window.
+//!
+//! egui only sees the pointer while it is over the viewport, which isn't
+//! enough once the window ignores the mouse or when reacting to a cursor
+//! that is merely nearby. Returns `None` where the platform doesn't expose
+//! the global position, e.g. on Wayland.
+
+use eframe::egui;
+
+/// The cursor in egui points, in the same desktop coordinates as
+/// `ViewportInfo::inner_rect`.
+pub fn global_position(pixels_per_point: f32) -> Option<egui::Pos2> {
+    platform::position(pixels_per_point)
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use eframe::egui;
+    use std::os::raw::{c_int, c_uint, c_ulong};
+    use x11_dl::xlib::{Display, Xlib};
+
+    struct Connection {
+        xlib: Xlib,
+        display: *mut Display,
+    }
+
+    thread_local! {
+        static CONNECTION: Option<Connection> = connect();
+    }
+
+    fn connect() -> Option<Connection> {
+        // Under Wayland the X server only sees the pointer over X windows.
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return None;
+        }
+
+        let xlib = Xlib::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        Some(Connection { xlib, display })
+    }
+
+    pub fn position(pixels_per_point: f32) -> Option<egui::Pos2> {
+        CONNECTION.with(|connection| {
+            let Connection { xlib, display } = connection.as_ref()?;
+
+            let (mut root_return, mut child_return): (c_ulong, c_ulong) = (0, 0);
+            let (mut root_x, mut root_y, mut win_x, mut win_y): (c_int, c_int, c_int, c_int) =
+                (0, 0, 0, 0);
+            let mut mask: c_uint = 0;
+
+            let found = unsafe {
+                let root = (xlib.XDefaultRootWindow)(*display);
+                (xlib.XQueryPointer)(
+                    *display,
+                    root,
+                    &mut root_return,
+                    &mut child_return,
+                    &mut root_x,
+                    &mut root_y,
+                    &mut win_x,
+                    &mut win_y,
+                    &mut mask,
+                )
+            };
+
+            (found != 0).then(|| {
+                egui::pos2(root_x as f32, root_y as f32) / pixels_per_point
+            })
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use eframe::egui;
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::UI::WindowsAndMessaging::GetCursorPos;
+
+    pub fn position(pixels_per_point: f32) -> Option<egui::Pos2> {
+        let mut point = POINT { x: 0, y: 0 };
+        let found = unsafe { GetCursorPos(&mut point) };
+        (found != 0).then(|| egui::pos2(point.x as f32, point.y as f32) / pixels_per_point)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use core_graphics::event::CGEvent;
+    use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+    use eframe::egui;
+
+    /// Quartz already reports points, so no scaling is needed.
+    pub fn position(_pixels_per_point: f32) -> Option<egui::Pos2> {
+        let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+        let location = CGEvent::new(source).ok()?.location();
+        Some(egui::pos2(location.x as f32, location.y as f32))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use eframe::egui;
+
+    pub fn position(_pixels_per_point: f32) -> Option<egui::Pos2> {
+        None
+    }
+}
@@ -0,0 +1,247 @@
+use image::{Rgba, RgbaImage};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+
+/// Extra themes next to the settings, in the same format as the embedded
+/// `themes.toml`.
+const USER_THEMES: &str = "themes.toml";
+
+/// One CPU pass over a frame, applied after it is cut from the sheet and
+/// before it is uploaded. Colors are unmultiplied RGBA.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Effect {
+    DropShadow {
+        offset: [i32; 2],
+        #[serde(default)]
+        blur: u32,
+        color: [u8; 4],
+    },
+    Outline {
+        width: u32,
+        color: [u8; 4],
+    },
+    /// Pulls colors towards `color`; its alpha is the strength.
+    Tint {
+        color: [u8; 4],
+    },
+    Grayscale {
+        amount: f32,
+    },
+    Opacity {
+        value: f32,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Theme {
+    pub name: String,
+    #[serde(default)]
+    pub effects: Vec<Effect>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    theme: Vec<Theme>,
+}
+
+impl Theme {
+    /// The embedded themes plus any in `themes.toml` in the data
+    /// directory. A user theme with the name of a built-in one replaces it.
+    /// A file that fails to parse is reported and skipped.
+    pub fn load(app_id: &str) -> Vec<Theme> {
+        let mut themes = parse(include_str!("../themes.toml")).expect("Failed to parse themes");
+
+        let Some(path) = eframe::storage_dir(app_id).map(|dir| dir.join(USER_THEMES)) else {
+            return themes;
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => match parse(&text) {
+                Ok(user) => merge(&mut themes, user),
+                Err(err) => eprintln!("tiny-clippy: skipping {}: {err}", path.display()),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("tiny-clippy: failed to read {}: {err}", path.display()),
+        }
+        themes
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Theme>, toml::de::Error> {
+    toml::from_str::<ThemeFile>(text).map(|file| file.theme)
+}
+
+/// Adds `user` to `themes`, in place of any theme with the same name.
+fn merge(themes: &mut Vec<Theme>, user: Vec<Theme>) {
+    for theme in user {
+        match themes.iter_mut().find(|existing| existing.name == theme.name) {
+            Some(existing) => *existing = theme,
+            None => themes.push(theme),
+        }
+    }
+}
+
+pub fn apply(effects: &[Effect], frame: &mut RgbaImage) {
+    for effect in effects {
+        match *effect {
+            Effect::DropShadow { offset, blur, color } => drop_shadow(frame, offset, blur, color),
+            Effect::Outline { width, color } => outline(frame, width, color),
+            Effect::Tint { color } => tint(frame, color),
+            Effect::Grayscale { amount } => grayscale(frame, amount),
+            Effect::Opacity { value } => opacity(frame, value),
+        }
+    }
+}
+
+fn alpha_at(frame: &RgbaImage, x: i64, y: i64) -> u8 {
+    if x < 0 || y < 0 || x >= frame.width() as i64 || y >= frame.height() as i64 {
+        return 0;
+    }
+    frame.get_pixel(x as u32, y as u32)[3]
+}
+
+/// Composites `top` over `bottom`, both unmultiplied.
+fn over(top: Rgba<u8>, bottom: Rgba<u8>) -> Rgba<u8> {
+    let top_a = top[3] as f32 / 255.0;
+    let bottom_a = bottom[3] as f32 / 255.0 * (1.0 - top_a);
+    let out_a = top_a + bottom_a;
+    if out_a <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mix = |c: usize| ((top[c] as f32 * top_a + bottom[c] as f32 * bottom_a) / out_a) as u8;
+    Rgba([mix(0), mix(1), mix(2), (out_a * 255.0) as u8])
+}
+
+/// Puts a layer of `color`, with per-pixel alpha from `mask`, behind the frame.
+fn underlay(frame: &mut RgbaImage, mask: &[f32], color: [u8; 4]) {
+    let width = frame.width() as usize;
+    for (x, y, pixel) in frame.enumerate_pixels_mut() {
+        let coverage = mask[y as usize * width + x as usize] * color[3] as f32;
+        let below = Rgba([color[0], color[1], color[2], coverage.min(255.0) as u8]);
+        *pixel = over(*pixel, below);
+    }
+}
+
+fn drop_shadow(frame: &mut RgbaImage, offset: [i32; 2], blur: u32, color: [u8; 4]) {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+
+    let mut mask = vec![0.0; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = alpha_at(frame, x as i64 - offset[0] as i64, y as i64 - offset[1] as i64);
+            mask[y * width + x] = alpha as f32 / 255.0;
+        }
+    }
+
+    box_blur(&mut mask, width, height, blur as usize);
+    underlay(frame, &mask, color);
+}
+
+fn outline(frame: &mut RgbaImage, width: u32, color: [u8; 4]) {
+    let radius = width as i64;
+    let (w, h) = (frame.width() as usize, frame.height() as usize);
+
+    let mut mask = vec![0.0; w * h];
+    for y in 0..h {
+        for x in 0..w {
+            let mut nearest = 0;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if dx * dx + dy * dy <= radius * radius {
+                        nearest = nearest.max(alpha_at(frame, x as i64 + dx, y as i64 + dy));
+                    }
+                }
+            }
+            mask[y * w + x] = nearest as f32 / 255.0;
+        }
+    }
+
+    underlay(frame, &mask, color);
+}
+
+/// Separable box blur, good enough for a soft shadow edge.
+fn box_blur(mask: &mut [f32], width: usize, height: usize, radius: usize) {
+    if radius == 0 {
+        return;
+    }
+
+    let span = (radius * 2 + 1) as f32;
+    let mut scratch = vec![0.0; mask.len()];
+
+    for y in 0..height {
+        for x in 0..width {
+            let from = x.saturating_sub(radius);
+            let to = (x + radius).min(width - 1);
+            let sum: f32 = mask[y * width + from..=y * width + to].iter().sum();
+            scratch[y * width + x] = sum / span;
+        }
+    }
+    for y in 0..height {
+        for x in 0..width {
+            let from = y.saturating_sub(radius);
+            let to = (y + radius).min(height - 1);
+            let sum: f32 = (from..=to).map(|row| scratch[row * width + x]).sum();
+            mask[y * width + x] = sum / span;
+        }
+    }
+}
+
+fn tint(frame: &mut RgbaImage, color: [u8; 4]) {
+    let strength = color[3] as f32 / 255.0;
+    for pixel in frame.pixels_mut() {
+        for c in 0..3 {
+            let value = pixel[c] as f32;
+            pixel[c] = (value + (color[c] as f32 - value) * strength) as u8;
+        }
+    }
+}
+
+fn grayscale(frame: &mut RgbaImage, amount: f32) {
+    let amount = amount.clamp(0.0, 1.0);
+    for pixel in frame.pixels_mut() {
+        let luma = 0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32;
+        for c in 0..3 {
+            let value = pixel[c] as f32;
+            pixel[c] = (value + (luma - value) * amount) as u8;
+        }
+    }
+}
+
+fn opacity(frame: &mut RgbaImage, value: f32) {
+    let value = value.clamp(0.0, 1.0);
+    for pixel in frame.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * value) as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_themes_add_to_and_replace_built_in_ones() {
+        let mut themes = parse(include_str!("../themes.toml")).unwrap();
+        let built_in = themes.len();
+        let user = parse(
+            r#"
+[[theme]]
+name = "Classic"
+effects = [{ kind = "opacity", value = 0.5 }]
+
+[[theme]]
+name = "Night"
+effects = [{ kind = "tint", color = [40, 60, 140, 90] }]
+"#,
+        )
+        .unwrap();
+        merge(&mut themes, user);
+
+        assert_eq!(themes.len(), built_in + 1);
+        assert_eq!(themes[0].name, "Classic");
+        assert_eq!(themes[0].effects, [Effect::Opacity { value: 0.5 }]);
+        assert_eq!(themes.last().unwrap().name, "Night");
+        assert!(parse("[[theme]]\neffects = []").is_err());
+    }
+}
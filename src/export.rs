@@ -0,0 +1,224 @@
+//! `tiny-clippy export`: writes a clip to an animated GIF, an APNG or a
+//! numbered PNG sequence without opening a window.
+//!
+//! Frames come from the same `extract_frame` path the window uses, in the
+//! order the animation plays them: the clip itself, then its return segment.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+use image::RgbaImage;
+
+use crate::manifest::ClipManifest;
+use crate::upscale::{self, Upscale};
+use crate::{extract_frame, load_sprite_sheet, Animation, AnimationClip, FRAME_DURATION_MS};
+
+/// Quantizer speed for GIF palettes, 1 (best) to 30 (fastest).
+const GIF_SPEED: i32 = 10;
+
+const USAGE: &str = "usage: tiny-clippy export --clip NAME|INDEX \
+[--format gif|apng|png-seq] [--scale N] [--filter NAME] OUT";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Gif,
+    Apng,
+    PngSequence,
+}
+
+impl Format {
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "gif" => Ok(Format::Gif),
+            "apng" => Ok(Format::Apng),
+            "png-seq" => Ok(Format::PngSequence),
+            other => Err(format!("unknown format `{other}`, expected gif, apng or png-seq")),
+        }
+    }
+}
+
+struct Options {
+    clip: String,
+    format: Format,
+    scale: u32,
+    filter: Upscale,
+    out: PathBuf,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let (mut clip, mut out) = (None, None);
+        let mut format = Format::Gif;
+        let mut scale = None;
+        let mut filter = Upscale::Nearest;
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--clip" => clip = Some(value("--clip")?),
+                "--format" => format = Format::parse(&value("--format")?)?,
+                "--scale" => {
+                    scale = Some(
+                        value("--scale")?
+                            .parse()
+                            .map_err(|_| "--scale must be a whole number".to_string())?,
+                    );
+                }
+                "--filter" => {
+                    let name = value("--filter")?;
+                    filter = Upscale::parse(&name).ok_or_else(|| {
+                        let names: Vec<&str> = Upscale::ALL.iter().map(|u| u.label()).collect();
+                        format!("unknown filter `{name}`, expected one of {}", names.join(", "))
+                    })?;
+                }
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                flag if flag.starts_with("--") => {
+                    return Err(format!("unknown option `{flag}`\n{USAGE}"));
+                }
+                path if out.is_none() => out = Some(PathBuf::from(path)),
+                extra => return Err(format!("unexpected argument `{extra}`\n{USAGE}")),
+            }
+        }
+
+        // Without a scale, keep whatever size the filter produces.
+        let scale = scale.unwrap_or(filter.factor());
+        if !(1..=16).contains(&scale) {
+            return Err("--scale must be between 1 and 16".to_string());
+        }
+        Ok(Options {
+            clip: clip.ok_or(format!("--clip is required\n{USAGE}"))?,
+            format,
+            scale,
+            filter,
+            out: out.ok_or(format!("an output path is required\n{USAGE}"))?,
+        })
+    }
+}
+
+/// A sprite frame held for `ticks` animation steps.
+struct Run {
+    frame: u32,
+    ticks: u32,
+}
+
+/// The frames `clip` shows when played, with repeats folded into longer
+/// holds.
+fn runs(clip: &AnimationClip) -> Vec<Run> {
+    let played = clip.start_frame..=clip.end_frame;
+    let returning = clip.return_segment.map(|(start, end)| start..=end);
+
+    let mut runs: Vec<Run> = Vec::new();
+    for frame in played.chain(returning.into_iter().flatten()) {
+        match runs.last_mut() {
+            Some(run) if run.frame == frame => run.ticks += 1,
+            _ => runs.push(Run { frame, ticks: 1 }),
+        }
+    }
+    runs
+}
+
+fn find_clip(manifest: &ClipManifest, clip: &str) -> Result<AnimationClip, String> {
+    let def = match clip.parse::<usize>() {
+        Ok(index) => manifest.clips.get(index),
+        Err(_) => manifest.find(clip),
+    };
+    def.map(|def| def.clip()).ok_or_else(|| {
+        let names: Vec<&str> = manifest.clips.iter().map(|def| def.name.as_str()).collect();
+        format!("no clip `{clip}`; available clips are {}", names.join(", "))
+    })
+}
+
+/// Cuts out a frame and brings it to `scale` the way the window does.
+fn render(sprite_sheet: &RgbaImage, frame: u32, scale: u32, filter: Upscale) -> RgbaImage {
+    let (frame_x, frame_y) = Animation::linear_to_grid(frame);
+    upscale::to_scale(filter, &extract_frame(sprite_sheet, frame_x, frame_y), scale)
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let clip = find_clip(&ClipManifest::embedded(), &options.clip)?;
+    let sprite_sheet = load_sprite_sheet();
+
+    let runs = runs(&clip);
+    let frames: Vec<(RgbaImage, u32)> = runs
+        .iter()
+        .map(|run| (render(&sprite_sheet, run.frame, options.scale, options.filter), run.ticks))
+        .collect();
+
+    let written = match options.format {
+        Format::Gif => write_gif(&options.out, &frames),
+        Format::Apng => write_apng(&options.out, &frames),
+        Format::PngSequence => write_png_sequence(&options.out, &frames),
+    };
+    written.map_err(|err| format!("failed to write {}: {err}", options.out.display()))
+}
+
+fn create(path: &Path) -> std::io::Result<BufWriter<File>> {
+    File::create(path).map(BufWriter::new)
+}
+
+fn write_gif(path: &Path, frames: &[(RgbaImage, u32)]) -> Result<(), String> {
+    let (width, height) = frames[0].0.dimensions();
+    let (width, height) = (
+        u16::try_from(width).map_err(|_| "frames are too wide for a GIF")?,
+        u16::try_from(height).map_err(|_| "frames are too tall for a GIF")?,
+    );
+
+    let file = create(path).map_err(|err| err.to_string())?;
+    let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(|err| err.to_string())?;
+    encoder.set_repeat(gif::Repeat::Infinite).map_err(|err| err.to_string())?;
+
+    // GIF delays are whole centiseconds; round against the running total so
+    // 75ms frames don't drift across a long clip.
+    let mut elapsed_ms = 0;
+    for (image, ticks) in frames {
+        let start_cs = (elapsed_ms + 5) / 10;
+        elapsed_ms += *ticks as u64 * FRAME_DURATION_MS;
+        let end_cs = (elapsed_ms + 5) / 10;
+
+        let mut pixels = image.as_raw().clone();
+        let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, GIF_SPEED);
+        frame.delay = u16::try_from(end_cs - start_cs).unwrap_or(u16::MAX);
+        // Clear to transparent between frames instead of drawing over the last.
+        frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&frame).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+fn write_apng(path: &Path, frames: &[(RgbaImage, u32)]) -> Result<(), String> {
+    let (width, height) = frames[0].0.dimensions();
+    let file = create(path).map_err(|err| err.to_string())?;
+
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(|err| err.to_string())?;
+    encoder.set_dispose_op(png::DisposeOp::Background).map_err(|err| err.to_string())?;
+    encoder.set_blend_op(png::BlendOp::Source).map_err(|err| err.to_string())?;
+
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    for (image, ticks) in frames {
+        let delay_ms = (*ticks as u64 * FRAME_DURATION_MS).min(u16::MAX as u64) as u16;
+        writer.set_frame_delay(delay_ms, 1000).map_err(|err| err.to_string())?;
+        writer.write_image_data(image.as_raw()).map_err(|err| err.to_string())?;
+    }
+    writer.finish().map_err(|err| err.to_string())
+}
+
+/// One file per animation step, so held frames repeat and playing the
+/// sequence back at a fixed rate keeps the clip's timing.
+fn write_png_sequence(dir: &Path, frames: &[(RgbaImage, u32)]) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+
+    let mut index = 0;
+    for (image, ticks) in frames {
+        for _ in 0..*ticks {
+            let path = dir.join(format!("frame-{index:04}.png"));
+            image.save(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+            index += 1;
+        }
+    }
+    Ok(())
+}
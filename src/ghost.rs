@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// How fast the displayed opacity catches up with the target, per second.
+const FADE_PER_SEC: f32 = 4.0;
+
+/// "Ghost mode": Clippy fades out as the cursor comes close so whatever is
+/// underneath stays visible, and can optionally stop taking clicks too.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GhostMode {
+    pub enabled: bool,
+    /// Opacity with the cursor right on top of Clippy.
+    pub opacity: f32,
+    /// Distance in points at which fading starts.
+    pub radius: f32,
+    pub click_through: bool,
+}
+
+impl Default for GhostMode {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            opacity: 0.25,
+            radius: 80.0,
+            click_through: false,
+        }
+    }
+}
+
+impl GhostMode {
+    /// The opacity to fade towards with the cursor `distance` points from
+    /// Clippy, or nowhere known.
+    pub fn target_opacity(&self, distance: Option<f32>) -> f32 {
+        match distance {
+            Some(distance) if self.enabled => {
+                let t = if self.radius > 0.0 {
+                    (distance / self.radius).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                self.opacity + (1.0 - self.opacity) * t
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Clicks should pass through once Clippy has faded noticeably.
+    pub fn wants_click_through(&self, opacity: f32) -> bool {
+        self.enabled && self.click_through && opacity < 0.99
+    }
+}
+
+/// Moves `current` towards `target` at a fixed rate.
+pub fn approach(current: f32, target: f32, secs: f32) -> f32 {
+    let step = FADE_PER_SEC * secs;
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
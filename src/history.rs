@@ -0,0 +1,356 @@
+//! Everything Clippy has said in a balloon, who asked him to and how it was
+//! answered. Kept as `[[entry]]` tables appended to a TOML file next to the
+//! window settings, so `say` and `ask` running in their own processes add
+//! to the same history as the Clippy sitting on the desktop. Writers take a
+//! lock on a file beside it, so a trim in one process never drops an entry
+//! another has just appended.
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::balloon::Outcome;
+
+const FILE_NAME: &str = "history.toml";
+const ENTRY_HEADER: &str = "[[entry]]";
+/// The file is trimmed back to `TRIM_TO_BYTES` of the newest entries once
+/// it grows past this.
+const MAX_BYTES: u64 = 512 * 1024;
+const TRIM_TO_BYTES: usize = 256 * 1024;
+const LIST_HEIGHT: f32 = 260.0;
+const LIST_WIDTH: f32 = 280.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the Unix epoch when the balloon came up.
+    pub shown: u64,
+    /// Who asked for the balloon: `say`, `ask`, `input`, the menu, or
+    /// whatever a script passed as `--source`.
+    pub source: String,
+    pub text: String,
+    /// `chosen`, `submitted`, `timeout` or `dismissed`, as hooks see it.
+    pub outcome: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+}
+
+impl Entry {
+    pub fn new(source: &str, text: &str, shown: SystemTime, outcome: &Outcome) -> Self {
+        Self {
+            shown: shown.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs()),
+            source: source.to_string(),
+            text: text.to_string(),
+            outcome: outcome.kind().to_string(),
+            answer: outcome.answer().map(str::to_string),
+        }
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        [Some(&self.text), Some(&self.source), self.answer.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|field| field.to_lowercase().contains(query))
+    }
+}
+
+#[derive(Deserialize)]
+struct Log {
+    #[serde(default)]
+    entry: Vec<Entry>,
+}
+
+#[derive(Serialize)]
+struct LogRef<'a> {
+    entry: &'a [Entry],
+}
+
+pub struct History {
+    /// `None` where the platform has no data directory; the history then
+    /// only lasts as long as the window.
+    path: Option<PathBuf>,
+    /// Oldest first.
+    entries: Vec<Entry>,
+    query: String,
+}
+
+impl History {
+    /// Nothing is read until the history is shown.
+    pub fn open(app_id: &str) -> Self {
+        Self {
+            path: eframe::storage_dir(app_id).map(|dir| dir.join(FILE_NAME)),
+            entries: Vec::new(),
+            query: String::new(),
+        }
+    }
+
+    /// Rereads the file, picking up balloons other processes have shown.
+    pub fn reload(&mut self) {
+        match self.read() {
+            Ok(entries) => self.entries = entries,
+            Err(err) => eprintln!("tiny-clippy: failed to read the history: {err}"),
+        }
+    }
+
+    pub fn record(&mut self, entry: Entry) {
+        self.entries.push(entry);
+        if let Err(err) = self.append_last() {
+            eprintln!("tiny-clippy: failed to save the history: {err}");
+        }
+    }
+
+    fn read(&self) -> io::Result<Vec<Entry>> {
+        let Some(path) = &self.path else {
+            return Ok(self.entries.clone());
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err),
+        };
+        Ok(parse(&text))
+    }
+
+    fn append_last(&mut self) -> io::Result<()> {
+        let (Some(path), Some(entry)) = (&self.path, self.entries.last()) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let _lock = lock(path)?;
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(serialize(std::slice::from_ref(entry))?.as_bytes())?;
+        if file.metadata()?.len() > MAX_BYTES {
+            self.trim()?;
+        }
+        Ok(())
+    }
+
+    /// Drops the oldest entries until the newest fit in `TRIM_TO_BYTES`,
+    /// along with any that no longer parse, replacing the file in one
+    /// rename so a reader never sees half of it. Called with the lock held.
+    fn trim(&mut self) -> io::Result<()> {
+        let Some(path) = self.path.clone() else {
+            return Ok(());
+        };
+        let entries = self.read()?;
+
+        let mut size = 0;
+        let mut keep = entries.len();
+        for entry in entries.iter().rev() {
+            size += serialize(std::slice::from_ref(entry))?.len();
+            if size > TRIM_TO_BYTES {
+                break;
+            }
+            keep -= 1;
+        }
+        self.entries = entries[keep..].to_vec();
+
+        let staging = path.with_extension("toml.tmp");
+        fs::write(&staging, serialize(&self.entries)?)?;
+        fs::rename(staging, path)
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        if let Some(path) = &self.path {
+            let removed = lock(path).and_then(|_lock| fs::remove_file(path));
+            if let Err(err) = removed {
+                if err.kind() != io::ErrorKind::NotFound {
+                    eprintln!("tiny-clippy: failed to clear the history: {err}");
+                }
+            }
+        }
+    }
+
+    /// Newest first, filtered by the search box.
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("Search"));
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+        ui.separator();
+
+        let query = self.query.trim().to_lowercase();
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+        let mut shown = 0;
+        egui::ScrollArea::vertical().max_height(LIST_HEIGHT).show(ui, |ui| {
+            ui.set_width(LIST_WIDTH);
+            for entry in self.entries.iter().rev().filter(|entry| entry.matches(&query)) {
+                if shown > 0 {
+                    ui.separator();
+                }
+                shown += 1;
+
+                ui.horizontal(|ui| {
+                    ui.weak(ago(now.saturating_sub(entry.shown)))
+                        .on_hover_text(utc(entry.shown));
+                    ui.strong(&entry.source);
+                    let response = match &entry.answer {
+                        Some(answer) => format!("{}: {answer}", entry.outcome),
+                        None => entry.outcome.clone(),
+                    };
+                    ui.weak(response);
+                });
+                ui.label(&entry.text);
+            }
+        });
+
+        if shown == 0 {
+            ui.weak(if self.entries.is_empty() { "Nothing said yet." } else { "No matches." });
+        }
+    }
+}
+
+/// Takes the lock beside the history at `path`, released when the file is
+/// dropped. The history itself can't be locked, since trimming replaces it.
+fn lock(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("toml.lock"))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Every entry in `text`. If the file as a whole doesn't parse, say after
+/// a crash in the middle of an append, it is split at the `[[entry]]`
+/// headers and whatever entries still parse are kept.
+fn parse(text: &str) -> Vec<Entry> {
+    if let Ok(log) = toml::from_str::<Log>(text) {
+        return log.entry;
+    }
+
+    let mut starts = Vec::new();
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.trim_end() == ENTRY_HEADER {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+    starts.push(text.len());
+
+    let mut entries = Vec::new();
+    for chunk in starts.windows(2).map(|bounds| &text[bounds[0]..bounds[1]]) {
+        match toml::from_str::<Log>(chunk) {
+            Ok(log) => entries.extend(log.entry),
+            Err(err) => eprintln!("tiny-clippy: skipping a damaged history entry: {err}"),
+        }
+    }
+    entries
+}
+
+fn serialize(entries: &[Entry]) -> io::Result<String> {
+    toml::to_string(&LogRef { entry: entries }).map_err(io::Error::other)
+}
+
+fn ago(secs: u64) -> String {
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ => format!("{} d ago", secs / 86400),
+    }
+}
+
+/// `YYYY-MM-DD HH:MM UTC`, using the days-to-civil conversion from Howard
+/// Hinnant's date algorithms.
+fn utc(secs: u64) -> String {
+    let (days, rest) = ((secs / 86400) as i64, secs % 86400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{year}-{month:02}-{day:02} {:02}:{:02} UTC", rest / 3600, rest % 3600 / 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(shown: u64, source: &str, text: &str, answer: Option<&str>) -> Entry {
+        Entry {
+            shown,
+            source: source.to_string(),
+            text: text.to_string(),
+            outcome: "chosen".to_string(),
+            answer: answer.map(str::to_string),
+        }
+    }
+
+    /// A history kept in its own directory under the temp directory.
+    fn temp_history(name: &str) -> History {
+        let dir = std::env::temp_dir().join(format!("tiny-clippy-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        History { path: Some(dir.join(FILE_NAME)), entries: Vec::new(), query: String::new() }
+    }
+
+    #[test]
+    fn utc_formats_dates() {
+        assert_eq!(utc(0), "1970-01-01 00:00 UTC");
+        assert_eq!(utc(951_782_400), "2000-02-29 00:00 UTC");
+        assert_eq!(utc(1_700_000_000), "2023-11-14 22:13 UTC");
+        assert_eq!(utc(1_735_689_599), "2024-12-31 23:59 UTC");
+    }
+
+    #[test]
+    fn matches_text_source_and_answer() {
+        let entry = entry(0, "ci", "Deploy to staging?", Some("Later"));
+        assert!(entry.matches("deploy"));
+        assert!(entry.matches("ci"));
+        assert!(entry.matches("later"));
+        assert!(entry.matches(""));
+        assert!(!entry.matches("chosen"));
+        assert!(!entry.matches("production"));
+    }
+
+    #[test]
+    fn trim_keeps_the_newest_entries() {
+        let mut history = temp_history("trim");
+        let text = "x".repeat(1024);
+        for shown in 0..600 {
+            history.record(entry(shown, "test", &text, None));
+        }
+
+        let path = history.path.clone().unwrap();
+        assert!(fs::metadata(&path).unwrap().len() <= MAX_BYTES);
+        history.reload();
+        let shown: Vec<u64> = history.entries.iter().map(|entry| entry.shown).collect();
+        assert!(shown[0] > 0, "nothing was trimmed");
+        assert_eq!(shown, (shown[0]..600).collect::<Vec<_>>());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn damaged_entries_are_skipped_and_trimmed_away() {
+        let mut history = temp_history("damaged");
+        let path = history.path.clone().unwrap();
+        history.record(entry(1, "test", "first", None));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{ENTRY_HEADER}\nshown = \"half written").unwrap();
+        history.record(entry(2, "test", "second", None));
+
+        history.reload();
+        let texts: Vec<&str> = history.entries.iter().map(|entry| entry.text.as_str()).collect();
+        assert_eq!(texts, ["first", "second"]);
+
+        history.trim().unwrap();
+        let log: Log = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(log.entry.len(), 2);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
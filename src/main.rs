@@ -1,44 +1,101 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use eframe::egui;
+#[cfg(feature = "eframe")]
+mod app;
+mod backend;
+#[cfg(feature = "eframe")]
+mod balloon;
+#[cfg(feature = "eframe")]
+#[cfg_attr(not(unix), allow(dead_code))]
+mod control;
+#[cfg(feature = "eframe")]
+mod cursor;
+#[cfg(feature = "eframe")]
+mod effects;
+mod export;
+#[cfg(feature = "eframe")]
+mod ghost;
+#[cfg(feature = "eframe")]
+mod history;
+mod manifest;
+#[cfg(feature = "eframe")]
+mod markdown;
+#[cfg(feature = "eframe")]
+mod monitor;
+#[cfg(feature = "eframe")]
+mod perf;
+mod personality;
+mod sheet;
+#[cfg(test)]
+mod snapshot;
+mod terminal;
+#[cfg(feature = "eframe")]
+mod tips;
+mod upscale;
+
+use backend::Backend;
+use manifest::{ClipManifest, CooldownSpec};
+#[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+use manifest::Interaction;
+#[cfg(feature = "eframe")]
+use manifest::LookDirection;
+use personality::{Mood, Personality};
+use rand::seq::SliceRandom;
 use rand::Rng;
+use sheet::{extract_frame, load_sprite_sheet, FRAMES_PER_ROW, FRAME_H, FRAME_W};
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 // Constants
-const FRAME_W: u32 = 124;
-const FRAME_H: u32 = 93;
-const FRAMES_PER_ROW: u32 = 27;
 const FRAME_DURATION_MS: u64 = 75;
 const IDLE_CHECK_MS: u64 = 95;
-const MIN_DELAY_BETWEEN_ANIMATIONS_SECS: u64 = 9;
+const QUIET_COOLDOWN_SCALE: f32 = 4.0;
 const ANIMATION_TRIGGER_CHANCE: f32 = 1.0;
-
-fn main() -> eframe::Result {
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_decorations(false)
-            .with_transparent(true)
-            .with_always_on_top()
-            .with_inner_size([FRAME_W as f32, FRAME_H as f32])
-            .with_resizable(false)
-            .with_mouse_passthrough(false),
-        ..Default::default()
+const REST_FRAME: u32 = 0;
+const REST_FADE_MS: u64 = 150;
+#[cfg(any(feature = "eframe", feature = "pixels", feature = "softbuffer"))]
+const DRAG_SETTLE_MS: u64 = 400;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        None => Backend::preferred().and_then(Backend::run),
+        Some("--backend") => args
+            .next()
+            .ok_or("--backend needs a value".to_string())
+            .and_then(|name| Backend::parse(&name))
+            .and_then(Backend::run),
+        #[cfg(feature = "eframe")]
+        Some("say") => app::say(args),
+        #[cfg(not(feature = "eframe"))]
+        Some("say" | "ask" | "input" | "tip") => {
+            Err("balloons need a build with the eframe feature".to_string())
+        }
+        #[cfg(feature = "eframe")]
+        Some("ask") => app::ask(args),
+        #[cfg(feature = "eframe")]
+        Some("input") => app::input(args),
+        #[cfg(feature = "eframe")]
+        Some("tip") => app::tip(args),
+        Some("export") => export::run(args),
+        Some("terminal") => terminal::run(args),
+        Some(other) => Err(format!("unknown command `{other}`")),
     };
-
-    eframe::run_native(
-        "Tiny Clippy",
-        options,
-        Box::new(|cc| Ok(Box::new(ClippyApp::new(cc)))),
-    )
+    if let Err(err) = result {
+        eprintln!("tiny-clippy: {err}");
+        std::process::exit(2);
+    }
 }
-
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct AnimationClip {
     start_frame: u32,
     end_frame: u32,
+    return_segment: Option<(u32, u32)>,
+    cooldown: CooldownSpec,
 }
 
 impl AnimationClip {
+    #[cfg(feature = "eframe")]
     fn length(&self) -> u32 {
         self.end_frame - self.start_frame + 1
     }
@@ -51,49 +108,66 @@ enum AnimationState {
         clip: AnimationClip,
         current_linear_frame: u32,
     },
+    Returning {
+        clip: AnimationClip,
+        current_linear_frame: u32,
+    },
+    Fading {
+        from_frame: u32,
+        started: Instant,
+    },
     Cooldown,
 }
-const ANIMATION_CLIPS: &[(u32, u32)] = &[
-    (0, 20),
-    (20, 62),
-    (63, 86),
-    (86, 135),
-    (135, 194),
-    (194, 217),
-    (217, 233),
-    (233, 249),
-    (249, 267),
-    (267, 306),
-    (306, 343),
-    (343, 359),
-    (359, 416),
-    (416, 434),
-    (434, 497),
-    (512, 535),
-    (535, 554),
-    (554, 613),
-    (613, 698),
-    (698, 717),
-    (718, 735),
-    (735, 790),
-    (790, 821),
-    (822, 885),
-];
 
 struct Animation {
     state: AnimationState,
     last_frame_time: Instant,
     last_idle_check: Instant,
     last_animation_end: Instant,
+    next_animation_at: Instant,
+    pending_cooldown: CooldownSpec,
+    /// The clip on screen is a look, which leaves the idle cooldown running
+    /// instead of starting a new one when it ends.
+    glancing: bool,
+    quiet: bool,
+    manifest: ClipManifest,
+    #[cfg(feature = "eframe")]
+    last_look: Option<LookDirection>,
+    queue: VecDeque<AnimationClip>,
+    /// Clip that starts over instead of ending, until `done_talking`.
+    looping: Option<AnimationClip>,
+    personality: Personality,
+    mood: Mood,
+    last_mood_tick: Instant,
+    /// How long to go between tips, which replace the idle clip at the end
+    /// of a cooldown. `None` never asks for one.
+    tip_interval: Option<Duration>,
+    last_tip: Instant,
+    tip_due: bool,
 }
 
 impl Animation {
-    fn new() -> Self {
+    fn new(manifest: ClipManifest, personality: Personality) -> Self {
         Self {
             state: AnimationState::Idle,
             last_frame_time: Instant::now(),
             last_idle_check: Instant::now(),
             last_animation_end: Instant::now(),
+            next_animation_at: Instant::now(),
+            pending_cooldown: manifest.cooldown.default,
+            glancing: false,
+            quiet: false,
+            manifest,
+            #[cfg(feature = "eframe")]
+            last_look: None,
+            queue: VecDeque::new(),
+            looping: None,
+            personality,
+            mood: Mood::new(personality),
+            last_mood_tick: Instant::now(),
+            tip_interval: None,
+            last_tip: Instant::now(),
+            tip_due: false,
         }
     }
 
@@ -106,35 +180,55 @@ impl Animation {
     fn update(&mut self) -> (u32, u32) {
         let now = Instant::now();
 
+        let mood_elapsed = now.duration_since(self.last_mood_tick).as_secs_f32();
+        self.mood.tick(self.personality, mood_elapsed);
+        self.last_mood_tick = now;
+
         if now.duration_since(self.last_frame_time) >= Duration::from_millis(FRAME_DURATION_MS) {
             self.advance_frame();
             self.last_frame_time = now;
         }
 
+        if let AnimationState::Fading { started, .. } = self.state {
+            if now.duration_since(started) >= Duration::from_millis(REST_FADE_MS) {
+                self.rest();
+            }
+        }
+
         if matches!(self.state, AnimationState::Idle | AnimationState::Cooldown) {
-            if now.duration_since(self.last_idle_check) >= Duration::from_millis(IDLE_CHECK_MS) {
-                self.maybe_start_animation(now);
-                self.last_idle_check = now;
+            if let Some(clip) = self.queue.pop_front() {
+                self.play(clip);
             }
         }
 
+        if matches!(self.state, AnimationState::Idle | AnimationState::Cooldown)
+            && now.duration_since(self.last_idle_check) >= Duration::from_millis(IDLE_CHECK_MS)
+        {
+            self.maybe_start_animation(now);
+            self.last_idle_check = now;
+        }
+
         self.get_sprite_coordinates()
     }
 
     fn advance_frame(&mut self) {
         match self.state {
-            AnimationState::Idle | AnimationState::Cooldown => {
-                // Stay at frame 0
+            AnimationState::Idle | AnimationState::Cooldown | AnimationState::Fading { .. } => {
+                // Stay at the rest frame
             }
             AnimationState::Playing { clip, current_linear_frame } => {
                 let new_frame = current_linear_frame + 1;
 
-                if new_frame > clip.end_frame {
+                if new_frame > clip.end_frame && self.looping == Some(clip) {
+                    self.state = AnimationState::Playing {
+                        clip,
+                        current_linear_frame: clip.start_frame,
+                    };
+                } else if new_frame > clip.end_frame {
                     // let start = clip.start_frame;
                     // let end = clip.end_frame;
 
-                    self.state = AnimationState::Cooldown;
-                    self.last_animation_end = Instant::now();
+                    self.return_to_rest(clip, current_linear_frame);
                     // println!("Animation complete: {} -> {}", start, end);
                 } else {
                     self.state = AnimationState::Playing {
@@ -143,75 +237,315 @@ impl Animation {
                     };
                 }
             }
+            AnimationState::Returning { clip, current_linear_frame } => {
+                let new_frame = current_linear_frame + 1;
+
+                if new_frame > clip.end_frame {
+                    self.rest();
+                } else {
+                    self.state = AnimationState::Returning {
+                        clip,
+                        current_linear_frame: new_frame,
+                    };
+                }
+            }
+        }
+    }
+
+    /// Leaves `clip` at `last_frame` without popping: plays the clip's
+    /// declared return segment, or crossfades to the rest frame if it has none.
+    fn return_to_rest(&mut self, clip: AnimationClip, last_frame: u32) {
+        match clip.return_segment {
+            Some((start, end)) => {
+                self.state = AnimationState::Returning {
+                    clip: AnimationClip {
+                        start_frame: start,
+                        end_frame: end,
+                        return_segment: None,
+                        cooldown: clip.cooldown,
+                    },
+                    current_linear_frame: start,
+                };
+            }
+            None if last_frame != REST_FRAME => {
+                self.state = AnimationState::Fading {
+                    from_frame: last_frame,
+                    started: Instant::now(),
+                };
+            }
+            None => self.rest(),
+        }
+    }
+
+    /// Settles into the cooldown and samples when the next idle clip is due,
+    /// unless the clip was only a look and the old deadline still stands.
+    fn rest(&mut self) {
+        let now = Instant::now();
+        self.state = AnimationState::Cooldown;
+        if std::mem::take(&mut self.glancing) {
+            return;
+        }
+        self.mood.on_clip_finished();
+
+        let mut cooldown = self.pending_cooldown.sample(&mut rand::thread_rng());
+        cooldown = cooldown.mul_f32(self.mood.cooldown_scale(self.personality));
+        if self.quiet {
+            cooldown = cooldown.mul_f32(QUIET_COOLDOWN_SCALE);
+        }
+
+        self.last_animation_end = now;
+        self.next_animation_at = now + cooldown;
+    }
+
+    /// Stops the running clip early, going through the same return path as
+    /// a clip that finished on its own.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    fn interrupt(&mut self) {
+        if let AnimationState::Playing { clip, current_linear_frame } = self.state {
+            self.return_to_rest(clip, current_linear_frame);
         }
     }
 
     fn maybe_start_animation(&mut self, now: Instant) {
         if matches!(self.state, AnimationState::Cooldown) {
-            let elapsed = now.duration_since(self.last_animation_end);
-            if elapsed < Duration::from_secs(MIN_DELAY_BETWEEN_ANIMATIONS_SECS) {
+            if now < self.next_animation_at {
                 return;
             }
             self.state = AnimationState::Idle;
+
+            if self.tip_interval.is_some_and(|every| now - self.last_tip >= every) {
+                // The talking clip that comes with the tip's balloon stands
+                // in for the idle clip.
+                self.last_tip = now;
+                self.tip_due = true;
+                return;
+            }
         }
 
         if matches!(self.state, AnimationState::Idle) {
             let mut rng = rand::thread_rng();
 
             if rng.gen::<f32>() < ANIMATION_TRIGGER_CHANCE {
-                if !ANIMATION_CLIPS.is_empty() {
-                    let clip_index = rng.gen_range(0..ANIMATION_CLIPS.len());
-                    let (start, end) = ANIMATION_CLIPS[clip_index];
-
-                    let clip = AnimationClip {
-                        start_frame: start,
-                        end_frame: end,
-                    };
-
-                    self.state = AnimationState::Playing {
-                        clip,
-                        current_linear_frame: start,
-                    };
-
-                    // let (start_col, start_row) = Self::linear_to_grid(start);
-                    // let (end_col, end_row) = Self::linear_to_grid(end);
+                let pool: Vec<_> = self.manifest.idle_clips().collect();
+                let mood = self.mood;
+                if let Ok(def) = pool.choose_weighted(&mut rng, |def| mood.clip_weight(def)) {
+                    let clip = def.clip();
+                    self.play(clip);
+
+                    // let (start_col, start_row) = Self::linear_to_grid(clip.start_frame);
+                    // let (end_col, end_row) = Self::linear_to_grid(clip.end_frame);
                     // println!(
                     //     "Starting animation {}: frames {}-{} (row {}:{} to row {}:{})",
-                    //     clip_index, start, end, start_row, start_col, end_row, end_col
+                    //     def.name, clip.start_frame, clip.end_frame, start_row, start_col, end_row, end_col
                     // );
                 }
             }
         }
     }
 
+    /// Stretches idle cooldowns while on, including the one already running.
+    #[cfg(feature = "eframe")]
+    fn set_quiet(&mut self, quiet: bool) {
+        if quiet == self.quiet {
+            return;
+        }
+        self.quiet = quiet;
+
+        let total = self.next_animation_at.duration_since(self.last_animation_end);
+        let total = if quiet {
+            total.mul_f32(QUIET_COOLDOWN_SCALE)
+        } else {
+            total.div_f32(QUIET_COOLDOWN_SCALE)
+        };
+        self.next_animation_at = self.last_animation_end + total;
+    }
+
+    /// Counts from now, so turning tips on doesn't bring one up at once.
+    #[cfg(feature = "eframe")]
+    fn set_tip_interval(&mut self, interval: Option<Duration>) {
+        if interval != self.tip_interval {
+            self.tip_interval = interval;
+            self.last_tip = Instant::now();
+        }
+    }
+
+    /// Whether a cooldown ended in a tip since the last call.
+    #[cfg(feature = "eframe")]
+    fn take_tip_due(&mut self) -> bool {
+        std::mem::take(&mut self.tip_due)
+    }
+
+    #[cfg(feature = "eframe")]
+    fn set_personality(&mut self, personality: Personality) {
+        self.personality = personality;
+        self.mood = Mood::new(personality);
+    }
+
+    fn play(&mut self, clip: AnimationClip) {
+        self.pending_cooldown = clip.cooldown;
+        self.glancing = false;
+        self.state = AnimationState::Playing {
+            clip,
+            current_linear_frame: clip.start_frame,
+        };
+    }
+
+    /// Plays `clip` once the current clip has returned to rest, without
+    /// waiting out the idle cooldown.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    fn enqueue(&mut self, clip: AnimationClip) {
+        self.queue.push_back(clip);
+    }
+
+    /// Cuts the current clip short and plays `clip` right after its return
+    /// to rest, dropping anything else that was queued.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    fn play_next(&mut self, clip: AnimationClip) {
+        self.queue.clear();
+        self.interrupt();
+        self.enqueue(clip);
+    }
+
+    /// Plays the manifest's clip for `interaction`. Hovering only queues its
+    /// clip when nothing else is going on; the rest cut in immediately.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    fn react(&mut self, interaction: Interaction) {
+        self.mood.on_interaction(self.personality, interaction);
+
+        let Some(clip) = self.manifest.interaction_clip(interaction).map(|def| def.clip()) else {
+            return;
+        };
+
+        match interaction {
+            Interaction::Hover => {
+                if !self.is_playing() && self.queue.is_empty() {
+                    self.enqueue(clip);
+                }
+            }
+            _ => self.play_next(clip),
+        }
+    }
+
+    /// Queues the manifest's talking clip to go with a new speech balloon.
+    #[cfg(feature = "eframe")]
+    fn talk(&mut self) {
+        if let Some(clip) = self.manifest.talk_clip().map(|def| def.clip()) {
+            self.enqueue(clip);
+        }
+    }
+
+    /// Like `talk`, but the clip keeps starting over until `done_talking`,
+    /// for as long as a balloon is still typing out its text. It cuts the
+    /// current clip short, since waiting for a long idle clip to finish
+    /// could outlast the typing.
+    #[cfg(feature = "eframe")]
+    fn talk_until_done(&mut self) {
+        if let Some(clip) = self.manifest.talk_clip().map(|def| def.clip()) {
+            self.looping = Some(clip);
+            self.play_next(clip);
+        }
+    }
+
+    /// Ends the talking loop, leaving the clip through its usual return to
+    /// rest, or dropping it if it hasn't started yet.
+    #[cfg(feature = "eframe")]
+    fn done_talking(&mut self) {
+        let Some(looping) = self.looping.take() else {
+            return;
+        };
+        match self.state {
+            AnimationState::Playing { clip, .. } if clip == looping => self.interrupt(),
+            _ => self.queue.retain(|clip| *clip != looping),
+        }
+    }
+
+    /// Turns towards the cursor. A look clip plays once per direction change
+    /// and never interrupts a clip that is already running.
+    #[cfg(feature = "eframe")]
+    fn look_at(&mut self, direction: Option<LookDirection>) {
+        match direction {
+            None => self.last_look = None,
+            Some(direction) if self.last_look != Some(direction) && !self.is_playing() => {
+                if let Some(def) = self.manifest.look_clip(direction) {
+                    let clip = def.clip();
+                    self.play(clip);
+                    self.glancing = true;
+                }
+                self.last_look = Some(direction);
+            }
+            Some(_) => {}
+        }
+    }
+
     fn get_sprite_coordinates(&self) -> (u32, u32) {
         match self.state {
-            AnimationState::Idle | AnimationState::Cooldown => (0, 0),
-            AnimationState::Playing { current_linear_frame, .. } => {
+            AnimationState::Idle | AnimationState::Cooldown | AnimationState::Fading { .. } => {
+                Self::linear_to_grid(REST_FRAME)
+            }
+            AnimationState::Playing { current_linear_frame, .. }
+            | AnimationState::Returning { current_linear_frame, .. } => {
                 Self::linear_to_grid(current_linear_frame)
             }
         }
     }
 
+    /// The frame being faded out over the rest frame, and its remaining opacity.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    fn fading_frame(&self) -> Option<((u32, u32), f32)> {
+        match self.state {
+            AnimationState::Fading { from_frame, started } => {
+                let fade = Duration::from_millis(REST_FADE_MS).as_secs_f32();
+                let t = started.elapsed().as_secs_f32() / fade;
+                Some((Self::linear_to_grid(from_frame), (1.0 - t).clamp(0.0, 1.0)))
+            }
+            _ => None,
+        }
+    }
+
+    /// True while a clip or its return-to-rest transition is on screen.
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
     fn is_playing(&self) -> bool {
-        matches!(self.state, AnimationState::Playing { .. })
+        matches!(
+            self.state,
+            AnimationState::Playing { .. }
+                | AnimationState::Returning { .. }
+                | AnimationState::Fading { .. }
+        )
     }
 
+    /// When the frame on screen next changes if nothing else happens, so the
+    /// app can sleep until then instead of polling.
+    fn next_change(&self) -> Instant {
+        let frame_due = self.last_frame_time + Duration::from_millis(FRAME_DURATION_MS);
+        let idle_check_due = self.last_idle_check + Duration::from_millis(IDLE_CHECK_MS);
+
+        match self.state {
+            AnimationState::Idle => idle_check_due,
+            AnimationState::Cooldown if !self.queue.is_empty() => Instant::now(),
+            AnimationState::Cooldown => self.next_animation_at.max(idle_check_due),
+            AnimationState::Playing { .. } | AnimationState::Returning { .. } => frame_due,
+            AnimationState::Fading { .. } => Instant::now(),
+        }
+    }
+
+    #[cfg(feature = "eframe")]
     fn time_until_next_animation(&self) -> Option<Duration> {
         if matches!(self.state, AnimationState::Cooldown) {
-            let elapsed = Instant::now().duration_since(self.last_animation_end);
-            let required = Duration::from_secs(MIN_DELAY_BETWEEN_ANIMATIONS_SECS);
-            if elapsed < required {
-                return Some(required - elapsed);
-            }
+            return self.next_animation_at.checked_duration_since(Instant::now());
         }
         None
     }
 
+    #[cfg(feature = "eframe")]
     fn get_current_state_info(&self) -> String {
         match self.state {
             AnimationState::Idle => "Idle (0, 0)".to_string(),
             AnimationState::Cooldown => "Waiting...".to_string(),
+            AnimationState::Returning { clip, current_linear_frame } => {
+                let progress = current_linear_frame - clip.start_frame;
+                format!("Returning to rest - {}/{}", progress, clip.length())
+            }
+            AnimationState::Fading { .. } => "Returning to rest (fade)".to_string(),
             AnimationState::Playing { clip, current_linear_frame } => {
                 let (col, row) = Self::linear_to_grid(current_linear_frame);
                 let progress = current_linear_frame - clip.start_frame;
@@ -225,127 +559,156 @@ impl Animation {
     }
 }
 
-struct ClippyApp {
-    animation: Animation,
-    texture: Option<egui::TextureHandle>,
-    sprite_sheet: image::RgbaImage,
-    show_debug: bool,
-}
-
-impl ClippyApp {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let img_bytes = include_bytes!("../clippy_map.png");
-        let sprite_sheet = image::load_from_memory(img_bytes)
-            .expect("Failed to load clippy sprite sheet")
-            .to_rgba8();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Self::setup_transparent_ui(&cc.egui_ctx);
+    const FIVE_SECONDS: CooldownSpec = CooldownSpec::Uniform { min: 5.0, max: 5.0 };
 
-        Self {
-            animation: Animation::new(),
-            texture: None,
-            sprite_sheet,
-            show_debug: false,
-        }
+    fn animation() -> Animation {
+        Animation::new(ClipManifest::embedded(), Personality::Calm)
     }
 
-    fn setup_transparent_ui(ctx: &egui::Context) {
-        let mut visuals = egui::Visuals::dark();
-        visuals.window_fill = egui::Color32::TRANSPARENT;
-        visuals.panel_fill = egui::Color32::TRANSPARENT;
-        ctx.set_visuals(visuals);
+    fn clip(start_frame: u32, end_frame: u32, return_segment: Option<(u32, u32)>) -> AnimationClip {
+        AnimationClip { start_frame, end_frame, return_segment, cooldown: FIVE_SECONDS }
     }
 
-    fn extract_frame(&self, frame_x: u32, frame_y: u32) -> egui::ColorImage {
-        let src_x = frame_x * FRAME_W;
-        let src_y = frame_y * FRAME_H;
-
-        let sub_img = image::imageops::crop_imm(
-            &self.sprite_sheet,
-            src_x,
-            src_y,
-            FRAME_W,
-            FRAME_H,
-        )
-            .to_image();
-
-        let size = [sub_img.width() as usize, sub_img.height() as usize];
-        let pixels = sub_img.as_flat_samples();
-
-        egui::ColorImage::from_rgba_unmultiplied(size, pixels.as_slice())
+    /// Steps frames until the clip and its return segment are over.
+    fn play_out(animation: &mut Animation) {
+        for _ in 0..1000 {
+            if !matches!(
+                animation.state,
+                AnimationState::Playing { .. } | AnimationState::Returning { .. }
+            ) {
+                return;
+            }
+            animation.advance_frame();
+        }
+        panic!("the clip never ended");
     }
 
-    fn update_texture(&mut self, ctx: &egui::Context, frame_x: u32, frame_y: u32) {
-        let color_image = self.extract_frame(frame_x, frame_y);
-
-        self.texture = Some(ctx.load_texture(
-            "clippy-frame",
-            color_image,
-            egui::TextureOptions::NEAREST,
-        ));
+    /// Steps frames until `linear` is on screen.
+    #[cfg(any(
+        feature = "eframe",
+        feature = "minifb",
+        feature = "pixels",
+        feature = "softbuffer"
+    ))]
+    fn play_out_to(animation: &mut Animation, linear: u32) {
+        let target = Animation::linear_to_grid(linear);
+        for _ in 0..1000 {
+            if animation.get_sprite_coordinates() == target {
+                return;
+            }
+            animation.advance_frame();
+        }
+        panic!("frame {linear} never came up");
     }
-}
-
-impl eframe::App for ClippyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        let (frame_x, frame_y) = self.animation.update();
-        self.update_texture(ctx, frame_x, frame_y);
 
-        egui::CentralPanel::default()
-            .frame(egui::Frame::none())
-            .show(ctx, |ui| {
-                if let Some(texture) = &self.texture {
-                    let img_widget = egui::Image::new(texture)
-                        .sense(egui::Sense::drag());
-
-                    let response = ui.add(img_widget);
-
-                    if response.drag_started() {
-                        ctx.send_viewport_cmd(egui::ViewportCommand::StartDrag);
-                    }
-
-                    response.context_menu(|ui| {
-                        ui.label(self.animation.get_current_state_info());
-
-                        if let Some(time_left) = self.animation.time_until_next_animation() {
-                            ui.label(format!("Next in: {:.1}s", time_left.as_secs_f32()));
-                        }
+    /// Lets a crossfade run out, as if `REST_FADE_MS` had passed.
+    fn finish_fade(animation: &mut Animation) {
+        let AnimationState::Fading { from_frame, .. } = animation.state else {
+            panic!("not fading: {:?}", animation.state);
+        };
+        let started = Instant::now() - Duration::from_millis(REST_FADE_MS);
+        animation.state = AnimationState::Fading { from_frame, started };
+        animation.update();
+    }
 
-                        ui.separator();
+    #[test]
+    fn return_segment_plays_before_the_cooldown() {
+        let mut animation = animation();
+        animation.play(clip(10, 12, Some((20, 21))));
 
-                        ui.checkbox(&mut self.show_debug, "Show debug info");
+        let mut frames = vec![animation.get_sprite_coordinates()];
+        while !matches!(animation.state, AnimationState::Cooldown) {
+            animation.advance_frame();
+            frames.push(animation.get_sprite_coordinates());
+        }
+        let expected: Vec<_> = [10, 11, 12, 20, 21, REST_FRAME]
+            .into_iter()
+            .map(Animation::linear_to_grid)
+            .collect();
+        assert_eq!(frames, expected);
+        assert!(animation.next_animation_at > Instant::now());
+    }
 
-                        ui.separator();
+    #[test]
+    fn clip_without_return_segment_fades_to_rest() {
+        let mut animation = animation();
+        animation.play(clip(10, 12, None));
+        play_out(&mut animation);
 
-                        if ui.button("Close Clippy").clicked() {
-                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
-                    });
-                }
+        assert!(matches!(animation.state, AnimationState::Fading { from_frame: 12, .. }));
+        assert_eq!(animation.get_sprite_coordinates(), Animation::linear_to_grid(REST_FRAME));
+        finish_fade(&mut animation);
+        assert!(matches!(animation.state, AnimationState::Cooldown));
+    }
 
-                if self.show_debug {
-                    egui::Window::new("Debug")
-                        .collapsible(false)
-                        .resizable(false)
-                        .show(ctx, |ui| {
-                            ui.label(self.animation.get_current_state_info());
-                            if let Some(time_left) = self.animation.time_until_next_animation() {
-                                ui.label(format!("Cooldown: {:.1}s", time_left.as_secs_f32()));
-                            }
-                        });
-                }
-            });
+    #[test]
+    fn clip_ending_on_the_rest_frame_needs_no_fade() {
+        let mut animation = animation();
+        animation.play(clip(REST_FRAME, REST_FRAME, None));
+        animation.advance_frame();
+        assert!(matches!(animation.state, AnimationState::Cooldown));
+    }
 
-        let repaint_delay = if self.animation.is_playing() {
-            Duration::from_millis(16)
-        } else {
-            Duration::from_millis(50)
-        };
+    #[cfg(feature = "eframe")]
+    #[test]
+    fn time_until_next_animation_counts_down_to_the_deadline() {
+        let mut animation = animation();
+        assert_eq!(animation.time_until_next_animation(), None);
+        animation.play(clip(REST_FRAME, REST_FRAME, None));
+        assert_eq!(animation.time_until_next_animation(), None);
+        animation.advance_frame();
+
+        let before = Instant::now();
+        let remaining = animation.time_until_next_animation().unwrap();
+        let after = Instant::now();
+        let deadline = animation.next_animation_at;
+        assert!(deadline - after <= remaining && remaining <= deadline - before);
+        assert_eq!(animation.next_change(), deadline);
+    }
 
-        ctx.request_repaint_after(repaint_delay);
+    #[cfg(feature = "eframe")]
+    #[test]
+    fn looking_keeps_the_idle_cooldown() {
+        let mut animation = animation();
+        animation.play(clip(REST_FRAME, REST_FRAME, None));
+        animation.advance_frame();
+        let deadline = animation.next_animation_at;
+
+        animation.look_at(Some(LookDirection::Left));
+        assert!(animation.is_playing());
+        play_out(&mut animation);
+        if matches!(animation.state, AnimationState::Fading { .. }) {
+            finish_fade(&mut animation);
+        }
+        assert!(matches!(animation.state, AnimationState::Cooldown));
+        assert_eq!(animation.next_animation_at, deadline);
     }
 
-    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
-        [0.0, 0.0, 0.0, 0.0]
+    #[cfg(any(
+        feature = "eframe",
+        feature = "minifb",
+        feature = "pixels",
+        feature = "softbuffer"
+    ))]
+    #[test]
+    fn interrupt_lets_a_return_segment_finish() {
+        let mut animation = animation();
+        animation.play(clip(10, 12, Some((20, 22))));
+        play_out_to(&mut animation, 21);
+        animation.interrupt();
+        assert!(matches!(
+            animation.state,
+            AnimationState::Returning { current_linear_frame: 21, .. }
+        ));
+
+        animation.play(clip(10, 12, None));
+        animation.advance_frame();
+        animation.interrupt();
+        assert!(matches!(animation.state, AnimationState::Fading { from_frame: 11, .. }));
+        assert!(animation.fading_frame().is_some());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,237 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::AnimationClip;
+
+#[cfg(feature = "eframe")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// The lighter backends only report clicks and drags.
+#[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+#[cfg_attr(not(feature = "eframe"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interaction {
+    Click,
+    DoubleClick,
+    DragStart,
+    DragEnd,
+    Hover,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClipDef {
+    pub name: String,
+    pub start: u32,
+    pub end: u32,
+    #[serde(default = "default_idle")]
+    pub idle: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, rename = "return")]
+    pub return_segment: Option<(u32, u32)>,
+    #[serde(skip)]
+    pub cooldown: CooldownSpec,
+}
+
+fn default_idle() -> bool {
+    true
+}
+
+impl ClipDef {
+    pub fn clip(&self) -> AnimationClip {
+        AnimationClip {
+            start_frame: self.start,
+            end_frame: self.end,
+            return_segment: self.return_segment,
+            cooldown: self.cooldown,
+        }
+    }
+}
+
+/// Seconds to wait after a clip before the next idle one.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum CooldownSpec {
+    Uniform {
+        min: f32,
+        max: f32,
+    },
+    Exponential {
+        mean: f32,
+        #[serde(default)]
+        min: f32,
+    },
+}
+
+impl Default for CooldownSpec {
+    fn default() -> Self {
+        CooldownSpec::Uniform { min: 6.0, max: 14.0 }
+    }
+}
+
+impl CooldownSpec {
+    pub fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let secs = match *self {
+            CooldownSpec::Uniform { min, max } if max > min => rng.gen_range(min..max),
+            CooldownSpec::Uniform { min, .. } => min,
+            CooldownSpec::Exponential { mean, min } => {
+                let u: f32 = rng.gen();
+                min - mean * (1.0 - u).ln()
+            }
+        };
+        Duration::from_secs_f32(secs.max(0.0))
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CooldownConfig {
+    #[serde(default)]
+    pub default: CooldownSpec,
+    #[serde(default)]
+    pub tag: HashMap<String, CooldownSpec>,
+}
+
+#[cfg(feature = "eframe")]
+#[derive(Debug, Default, Deserialize)]
+pub struct LookClips {
+    pub up: Option<String>,
+    pub down: Option<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+#[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+#[derive(Debug, Default, Deserialize)]
+pub struct InteractionClips {
+    pub click: Option<String>,
+    pub double_click: Option<String>,
+    pub drag_start: Option<String>,
+    pub drag_end: Option<String>,
+    pub hover: Option<String>,
+}
+
+#[cfg(feature = "eframe")]
+#[derive(Debug, Default, Deserialize)]
+pub struct SpeechClips {
+    pub talk: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClipManifest {
+    #[serde(rename = "clip")]
+    pub clips: Vec<ClipDef>,
+    /// Only the eframe app follows the cursor or shows balloons, so the
+    /// lighter backends skip these tables.
+    #[cfg(feature = "eframe")]
+    #[serde(default)]
+    pub look: LookClips,
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    #[serde(default)]
+    pub interaction: InteractionClips,
+    #[cfg(feature = "eframe")]
+    #[serde(default)]
+    pub speech: SpeechClips,
+    #[serde(default)]
+    pub cooldown: CooldownConfig,
+}
+
+impl ClipManifest {
+    pub fn embedded() -> Self {
+        let mut manifest: Self =
+            toml::from_str(include_str!("../clips.toml")).expect("Failed to parse clip manifest");
+        manifest.resolve_cooldowns();
+        manifest
+    }
+
+    /// Gives every clip the cooldown of its first tag that has one, or the
+    /// default cooldown.
+    fn resolve_cooldowns(&mut self) {
+        for def in &mut self.clips {
+            def.cooldown = def
+                .tags
+                .iter()
+                .find_map(|tag| self.cooldown.tag.get(tag))
+                .copied()
+                .unwrap_or(self.cooldown.default);
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ClipDef> {
+        self.clips.iter().find(|def| def.name == name)
+    }
+
+    pub fn idle_clips(&self) -> impl Iterator<Item = &ClipDef> {
+        self.clips.iter().filter(|def| def.idle)
+    }
+
+    #[cfg(feature = "eframe")]
+    pub fn look_clip(&self, direction: LookDirection) -> Option<&ClipDef> {
+        let name = match direction {
+            LookDirection::Up => &self.look.up,
+            LookDirection::Down => &self.look.down,
+            LookDirection::Left => &self.look.left,
+            LookDirection::Right => &self.look.right,
+        };
+        name.as_deref().and_then(|name| self.find(name))
+    }
+
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    pub fn interaction_clip(&self, interaction: Interaction) -> Option<&ClipDef> {
+        let name = match interaction {
+            Interaction::Click => &self.interaction.click,
+            Interaction::DoubleClick => &self.interaction.double_click,
+            Interaction::DragStart => &self.interaction.drag_start,
+            Interaction::DragEnd => &self.interaction.drag_end,
+            Interaction::Hover => &self.interaction.hover,
+        };
+        name.as_deref().and_then(|name| self.find(name))
+    }
+
+    #[cfg(feature = "eframe")]
+    pub fn talk_clip(&self) -> Option<&ClipDef> {
+        self.speech.talk.as_deref().and_then(|name| self.find(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn samples(spec: CooldownSpec) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(30);
+        (0..2000).map(|_| spec.sample(&mut rng).as_secs_f32()).collect()
+    }
+
+    #[test]
+    fn uniform_cooldowns_cover_their_range() {
+        let samples = samples(CooldownSpec::Uniform { min: 6.0, max: 14.0 });
+        assert!(samples.iter().all(|secs| (6.0..14.0).contains(secs)));
+        assert!(samples.iter().any(|secs| *secs < 7.0));
+        assert!(samples.iter().any(|secs| *secs > 13.0));
+    }
+
+    #[test]
+    fn empty_or_negative_ranges_fall_back_to_min() {
+        assert!(samples(CooldownSpec::Uniform { min: 5.0, max: 5.0 }).iter().all(|s| *s == 5.0));
+        assert!(samples(CooldownSpec::Uniform { min: 5.0, max: 2.0 }).iter().all(|s| *s == 5.0));
+        assert!(samples(CooldownSpec::Uniform { min: -2.0, max: -1.0 }).iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn exponential_cooldowns_keep_their_min_and_mean() {
+        let samples = samples(CooldownSpec::Exponential { mean: 6.0, min: 3.0 });
+        assert!(samples.iter().all(|secs| *secs >= 3.0));
+        let mean = samples.iter().map(|secs| secs - 3.0).sum::<f32>() / samples.len() as f32;
+        assert!((5.5..6.5).contains(&mean), "mean {mean}");
+    }
+}
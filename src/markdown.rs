@@ -0,0 +1,781 @@
+//! The Markdown subset balloons understand: paragraphs, `-` bullet lists,
+//! fenced code blocks with Rust highlighting, and inline bold, italics,
+//! code and links. Anything else is shown as written.
+//!
+//! Each block is laid out on its own so list items can hang their wrapped
+//! lines under the first and code blocks get a box of their own.
+
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use std::ops::Range;
+use std::sync::Arc;
+
+pub const FONT_SIZE: f32 = 13.0;
+const CODE_FONT_SIZE: f32 = 12.0;
+const BLOCK_GAP: f32 = 4.0;
+const BULLET_INDENT: f32 = 14.0;
+const BULLET_RADIUS: f32 = 2.5;
+/// Code gets a wider balloon than prose before it wraps.
+const MAX_CODE_WIDTH: f32 = 300.0;
+const CODE_PADDING: f32 = 4.0;
+/// Bold is drawn a second time this far to the right, since the bundled
+/// fonts have no bold face.
+const OVERSTRIKE: f32 = 0.6;
+
+const LINK: egui::Color32 = egui::Color32::from_rgb(0, 0, 238);
+const CODE_FILL: egui::Color32 = egui::Color32::from_rgb(242, 234, 184);
+const KEYWORD: egui::Color32 = egui::Color32::from_rgb(0, 0, 190);
+const TYPE: egui::Color32 = egui::Color32::from_rgb(38, 110, 140);
+const STRING: egui::Color32 = egui::Color32::from_rgb(163, 21, 21);
+const NUMBER: egui::Color32 = egui::Color32::from_rgb(9, 120, 80);
+const COMMENT: egui::Color32 = egui::Color32::from_rgb(0, 120, 0);
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+    "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+    "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true",
+    "type", "unsafe", "use", "where", "while",
+];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Span {
+    text: String,
+    bold: bool,
+    italic: bool,
+    code: bool,
+    link: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Block {
+    Paragraph(Vec<Span>),
+    Bullet(Vec<Span>),
+    Code { rust: bool, text: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    blocks: Vec<Block>,
+}
+
+impl Document {
+    pub fn parse(text: &str) -> Self {
+        let mut blocks = Vec::new();
+        // The paragraph or bullet being collected, with soft line breaks
+        // already joined, and whether it is a bullet.
+        let mut pending: Option<(String, bool)> = None;
+        let flush = |pending: &mut Option<(String, bool)>, blocks: &mut Vec<Block>| {
+            if let Some((text, bullet)) = pending.take() {
+                let spans = parse_inline(&text);
+                blocks.push(if bullet { Block::Bullet(spans) } else { Block::Paragraph(spans) });
+            }
+        };
+
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim();
+            let item = ["- ", "* ", "+ "].iter().find_map(|marker| trimmed.strip_prefix(marker));
+
+            if let Some(lang) = trimmed.strip_prefix("```") {
+                flush(&mut pending, &mut blocks);
+                let code: Vec<&str> = lines
+                    .by_ref()
+                    .take_while(|line| !line.trim_start().starts_with("```"))
+                    .collect();
+                let rust = matches!(lang.trim(), "rust" | "rs");
+                blocks.push(Block::Code { rust, text: code.join("\n") });
+            } else if trimmed.is_empty() {
+                flush(&mut pending, &mut blocks);
+            } else if let Some(item) = item {
+                flush(&mut pending, &mut blocks);
+                pending = Some((item.trim().to_string(), true));
+            } else {
+                // Only indented lines carry on a bullet.
+                let indented = line.starts_with(char::is_whitespace);
+                match &mut pending {
+                    Some((text, bullet)) if indented || !*bullet => {
+                        text.push(' ');
+                        text.push_str(trimmed);
+                    }
+                    _ => {
+                        flush(&mut pending, &mut blocks);
+                        pending = Some((trimmed.to_string(), false));
+                    }
+                }
+            }
+        }
+        flush(&mut pending, &mut blocks);
+
+        Self { blocks }
+    }
+
+    /// Characters on screen once the whole document is shown.
+    pub fn char_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|block| match block {
+                Block::Paragraph(spans) | Block::Bullet(spans) => {
+                    spans.iter().map(|span| span.text.chars().count()).sum()
+                }
+                Block::Code { text, .. } => text.chars().count(),
+            })
+            .sum()
+    }
+
+    /// Lays the document out to wrap prose at `max_width` points, showing
+    /// only its first `shown` characters. Colors are faded by `opacity`.
+    pub fn layout(
+        &self,
+        ctx: &egui::Context,
+        max_width: f32,
+        shown: usize,
+        ink: egui::Color32,
+        opacity: f32,
+    ) -> Layout {
+        let mut budget = shown;
+        let mut blocks = Vec::new();
+        let mut y = 0.0;
+
+        for block in &self.blocks {
+            let (built, x, padding) = match block {
+                Block::Paragraph(spans) => {
+                    (span_jobs(spans, max_width, &mut budget, ink, opacity), 0.0, 0.0)
+                }
+                Block::Bullet(spans) => {
+                    let width = max_width - BULLET_INDENT;
+                    (span_jobs(spans, width, &mut budget, ink, opacity), BULLET_INDENT, 0.0)
+                }
+                Block::Code { rust, text } => {
+                    let width = MAX_CODE_WIDTH - CODE_PADDING * 2.0;
+                    let job = code_job(text, *rust, width, &mut budget, ink, opacity);
+                    (job, CODE_PADDING, CODE_PADDING)
+                }
+            };
+
+            let galley = ctx.fonts(|fonts| fonts.layout_job(built.job));
+            let bold = built.bold.map(|job| ctx.fonts(|fonts| fonts.layout_job(job)));
+            let pos = egui::vec2(x, y + padding);
+            y += galley.size().y + padding * 2.0 + BLOCK_GAP;
+            blocks.push(LaidBlock {
+                pos,
+                galley,
+                bold,
+                links: built.links,
+                kind: match block {
+                    Block::Paragraph(_) => BlockKind::Paragraph,
+                    Block::Bullet(_) => BlockKind::Bullet,
+                    Block::Code { .. } => BlockKind::Code,
+                },
+            });
+        }
+
+        let width = blocks
+            .iter()
+            .map(|block| {
+                let padding = if block.kind == BlockKind::Code { CODE_PADDING } else { 0.0 };
+                block.pos.x + block.galley.size().x + padding
+            })
+            .fold(0.0, f32::max);
+        Layout {
+            blocks,
+            size: egui::vec2(width, (y - BLOCK_GAP).max(0.0)),
+            ink,
+            opacity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Paragraph,
+    Bullet,
+    Code,
+}
+
+/// A block's text ready for layout. Link ranges count characters.
+struct BuiltJob {
+    job: LayoutJob,
+    /// Only the bold parts, to draw over the rest slightly offset.
+    bold: Option<LayoutJob>,
+    links: Vec<(Range<usize>, String)>,
+}
+
+struct LaidBlock {
+    /// Top-left of the text, relative to the layout.
+    pos: egui::Vec2,
+    galley: Arc<egui::Galley>,
+    bold: Option<Arc<egui::Galley>>,
+    links: Vec<(Range<usize>, String)>,
+    kind: BlockKind,
+}
+
+pub struct Layout {
+    blocks: Vec<LaidBlock>,
+    pub size: egui::Vec2,
+    ink: egui::Color32,
+    opacity: f32,
+}
+
+impl Layout {
+    /// Paints the layout with its top-left at `origin` and returns the
+    /// address of a link that was clicked.
+    pub fn show(&self, ui: &egui::Ui, origin: egui::Pos2) -> Option<String> {
+        let painter = ui.painter();
+        let mut clicked = None;
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let pos = origin + block.pos;
+            match block.kind {
+                BlockKind::Code => {
+                    let rect = egui::Rect::from_min_size(
+                        egui::pos2(origin.x, pos.y - CODE_PADDING),
+                        egui::vec2(self.size.x, block.galley.size().y + CODE_PADDING * 2.0),
+                    );
+                    painter.rect_filled(rect, 3.0, CODE_FILL.gamma_multiply(self.opacity));
+                }
+                BlockKind::Bullet => {
+                    if let Some(row) = block.galley.rows.first() {
+                        let center = egui::pos2(
+                            origin.x + BULLET_INDENT / 2.0,
+                            pos.y + row.rect.center().y,
+                        );
+                        painter.circle_filled(center, BULLET_RADIUS, self.ink);
+                    }
+                }
+                BlockKind::Paragraph => {}
+            }
+
+            painter.galley(pos, block.galley.clone(), self.ink);
+            if let Some(bold) = &block.bold {
+                painter.galley(pos + egui::vec2(OVERSTRIKE, 0.0), bold.clone(), self.ink);
+            }
+
+            for (link, (chars, url)) in block.links.iter().enumerate() {
+                for (row, rect) in char_rects(&block.galley, chars).into_iter().enumerate() {
+                    let id = ui.id().with(("balloon-link", index, link, row));
+                    let response = ui
+                        .interact(rect.translate(pos.to_vec2()), id, egui::Sense::click())
+                        .on_hover_cursor(egui::CursorIcon::PointingHand)
+                        .on_hover_text(url);
+                    if response.clicked() {
+                        clicked = Some(url.clone());
+                    }
+                }
+            }
+        }
+        clicked
+    }
+}
+
+/// The area covered by a range of characters in `galley`, one rectangle
+/// per row it runs across.
+fn char_rects(galley: &egui::Galley, chars: &Range<usize>) -> Vec<egui::Rect> {
+    let mut rects = Vec::new();
+    let mut row_start = 0;
+    for row in &galley.rows {
+        let covered = row
+            .glyphs
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| chars.contains(&(row_start + index)))
+            .map(|(_, glyph)| {
+                egui::Rect::from_min_size(
+                    egui::pos2(glyph.pos.x, row.rect.top()),
+                    egui::vec2(glyph.advance_width, row.rect.height()),
+                )
+            })
+            .reduce(|a, b| a.union(b));
+        rects.extend(covered);
+        row_start += row.char_count_including_newline();
+    }
+    rects
+}
+
+/// The same layout-wise, but invisible: for text not typed out yet.
+fn hidden(mut format: TextFormat) -> TextFormat {
+    format.color = egui::Color32::TRANSPARENT;
+    format.background = egui::Color32::TRANSPARENT;
+    format.underline = egui::Stroke::NONE;
+    format
+}
+
+/// Appends `text`, showing only as many characters as `budget` has left,
+/// and returns whether any were shown.
+fn append(job: &mut LayoutJob, text: &str, format: TextFormat, budget: &mut usize) -> bool {
+    let split = text.char_indices().nth(*budget).map_or(text.len(), |(index, _)| index);
+    *budget -= text[..split].chars().count();
+    if split > 0 {
+        job.append(&text[..split], 0.0, format.clone());
+    }
+    if split < text.len() {
+        job.append(&text[split..], 0.0, hidden(format));
+    }
+    split > 0
+}
+
+fn span_jobs(
+    spans: &[Span],
+    wrap_width: f32,
+    budget: &mut usize,
+    ink: egui::Color32,
+    opacity: f32,
+) -> BuiltJob {
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = wrap_width;
+    let mut bold = job.clone();
+    let mut any_bold = false;
+    let mut links = Vec::new();
+    let mut chars = 0;
+
+    for span in spans {
+        let font = if span.code {
+            egui::FontId::monospace(CODE_FONT_SIZE)
+        } else {
+            egui::FontId::proportional(FONT_SIZE)
+        };
+        let mut format = TextFormat::simple(font, ink);
+        format.italics = span.italic;
+        if span.code {
+            format.background = CODE_FILL.gamma_multiply(opacity);
+        }
+        if let Some(url) = &span.link {
+            format.color = LINK.gamma_multiply(opacity);
+            format.underline = egui::Stroke::new(1.0, format.color);
+            let len = span.text.chars().count();
+            links.push((chars..chars + len, url.clone()));
+        }
+        chars += span.text.chars().count();
+
+        let mut bold_budget = *budget;
+        if span.bold {
+            any_bold |= append(&mut bold, &span.text, format.clone(), &mut bold_budget);
+        } else {
+            bold.append(&span.text, 0.0, hidden(format.clone()));
+        }
+        append(&mut job, &span.text, format, budget);
+    }
+
+    BuiltJob {
+        job,
+        bold: any_bold.then_some(bold),
+        links,
+    }
+}
+
+fn code_job(
+    code: &str,
+    rust: bool,
+    wrap_width: f32,
+    budget: &mut usize,
+    ink: egui::Color32,
+    opacity: f32,
+) -> BuiltJob {
+    let mut job = LayoutJob::default();
+    job.wrap.max_width = wrap_width;
+
+    let tokens = if rust { highlight_rust(code) } else { vec![(0..code.len(), Token::Plain)] };
+    for (range, token) in tokens {
+        let color = match token {
+            Token::Plain => ink,
+            Token::Keyword => KEYWORD.gamma_multiply(opacity),
+            Token::Type => TYPE.gamma_multiply(opacity),
+            Token::Str => STRING.gamma_multiply(opacity),
+            Token::Number => NUMBER.gamma_multiply(opacity),
+            Token::Comment => COMMENT.gamma_multiply(opacity),
+        };
+        let format = TextFormat::simple(egui::FontId::monospace(CODE_FONT_SIZE), color);
+        append(&mut job, &code[range], format, budget);
+    }
+
+    BuiltJob {
+        job,
+        bold: None,
+        links: Vec::new(),
+    }
+}
+
+/// Inline markup: `code`, **bold**, *italics*, [links](url), <url> and bare
+/// http(s) addresses. Markers without a partner are kept as text, and so
+/// are links to anything but the web or mail, since balloon text can come
+/// from any script.
+fn parse_inline(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans: Vec<Span> = Vec::new();
+    let mut current = Span::default();
+    let (mut bold, mut italic) = (false, false);
+
+    // Starts a new span if the style changes, so each span has one style.
+    let push = |spans: &mut Vec<Span>, span: Span| match spans.last_mut() {
+        Some(last)
+            if last.bold == span.bold
+                && last.italic == span.italic
+                && !last.code
+                && !span.code
+                && last.link.is_none()
+                && span.link.is_none() =>
+        {
+            last.text.push_str(&span.text);
+        }
+        _ if span.text.is_empty() => {}
+        _ => spans.push(span),
+    };
+    let styled = |text: String, bold: bool, italic: bool| Span {
+        text,
+        bold,
+        italic,
+        ..Span::default()
+    };
+
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+
+        if c == '\\' && next.is_some_and(|n| n.is_ascii_punctuation()) {
+            current.text.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+
+        if c == '`' {
+            if let Some(end) = find(&chars, i + 1, &['`']) {
+                push(&mut spans, std::mem::take(&mut current));
+                let code: String = chars[i + 1..end].iter().collect();
+                push(&mut spans, Span { text: code, bold, italic, code: true, link: None });
+                current = styled(String::new(), bold, italic);
+                i = end + 1;
+                continue;
+            }
+        }
+
+        if c == '[' {
+            if let Some((label, url, end)) = markdown_link(&chars, i) {
+                push(&mut spans, std::mem::take(&mut current));
+                push(&mut spans, Span { text: label, bold, italic, code: false, link: Some(url) });
+                current = styled(String::new(), bold, italic);
+                i = end;
+                continue;
+            }
+        }
+
+        let at_word_start = prev.is_none_or(|p| !p.is_alphanumeric());
+        if c == '<' {
+            if let Some(end) = find(&chars, i + 1, &['>']) {
+                let url: String = chars[i + 1..end].iter().collect();
+                if is_url(&url) {
+                    push(&mut spans, std::mem::take(&mut current));
+                    let link = Some(url.clone());
+                    push(&mut spans, Span { text: url, bold, italic, code: false, link });
+                    current = styled(String::new(), bold, italic);
+                    i = end + 1;
+                    continue;
+                }
+            }
+        }
+        if at_word_start && (c == 'h') {
+            let rest: String = chars[i..].iter().take(8).collect();
+            if is_url(&rest) {
+                let mut end = i;
+                while end < chars.len() && !chars[end].is_whitespace() {
+                    end += 1;
+                }
+                while end > i && ".,;:!?)'\"".contains(chars[end - 1]) {
+                    end -= 1;
+                }
+                let url: String = chars[i..end].iter().collect();
+                push(&mut spans, std::mem::take(&mut current));
+                let link = Some(url.clone());
+                push(&mut spans, Span { text: url, bold, italic, code: false, link });
+                current = styled(String::new(), bold, italic);
+                i = end;
+                continue;
+            }
+        }
+
+        if c == '*' || c == '_' {
+            let double = next == Some(c);
+            let marker: &[char] = if double { &[c, c] } else { &[c] };
+            let after = chars.get(i + marker.len()).copied();
+            let on = if double { bold } else { italic };
+            // Underscores only count at word edges, so snake_case stays put.
+            let opens = !on
+                && after.is_some_and(|a| !a.is_whitespace())
+                && (c == '*' || at_word_start)
+                && find_seq(&chars, i + marker.len(), marker).is_some();
+            let closes = on
+                && prev.is_some_and(|p| !p.is_whitespace())
+                && (c == '*' || after.is_none_or(|a| !a.is_alphanumeric()));
+            if opens || closes {
+                push(&mut spans, std::mem::take(&mut current));
+                if double {
+                    bold = !bold;
+                } else {
+                    italic = !italic;
+                }
+                current = styled(String::new(), bold, italic);
+                i += marker.len();
+                continue;
+            }
+        }
+
+        current.text.push(c);
+        i += 1;
+    }
+    push(&mut spans, current);
+    spans
+}
+
+fn is_url(text: &str) -> bool {
+    text.starts_with("https://") || text.starts_with("http://")
+}
+
+/// What `[label](url)` may point at; `file:` and custom URI handlers could
+/// do far more than open a page.
+fn is_link_target(url: &str) -> bool {
+    is_url(url) || url.starts_with("mailto:")
+}
+
+/// Index of the first of `targets` at or after `from`.
+fn find(chars: &[char], from: usize, targets: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&index| targets.contains(&chars[index]))
+}
+
+/// Index of the first occurrence of `seq` at or after `from`.
+fn find_seq(chars: &[char], from: usize, seq: &[char]) -> Option<usize> {
+    (from..chars.len()).find(|&index| chars[index..].starts_with(seq))
+}
+
+/// `[label](url)` starting at `start`: the label, the address and the index
+/// just past the closing parenthesis.
+fn markdown_link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let close = find(chars, start + 1, &[']'])?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = find(chars, close + 2, &[')'])?;
+    let label: String = chars[start + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+    let url = url.trim();
+    is_link_target(url).then(|| (label, url.to_string(), end + 1))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Plain,
+    Keyword,
+    Type,
+    Str,
+    Number,
+    Comment,
+}
+
+/// A rough Rust tokenizer, enough to color keywords, types, literals and
+/// comments. Returns byte ranges covering all of `code`.
+fn highlight_rust(code: &str) -> Vec<(Range<usize>, Token)> {
+    let chars: Vec<(usize, char)> = code.char_indices().collect();
+    let at = |index: usize| chars.get(index).map(|&(_, c)| c);
+    let offset = |index: usize| chars.get(index).map_or(code.len(), |&(offset, _)| offset);
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+
+    let mut tokens: Vec<(Range<usize>, Token)> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i].1;
+        let token = if c == '/' && at(i + 1) == Some('/') {
+            while i < chars.len() && at(i) != Some('\n') {
+                i += 1;
+            }
+            Token::Comment
+        } else if c == '/' && at(i + 1) == Some('*') {
+            i += 2;
+            while i < chars.len() && !(at(i) == Some('*') && at(i + 1) == Some('/')) {
+                i += 1;
+            }
+            i += 2;
+            Token::Comment
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() {
+                match at(i) {
+                    Some('\\') => i += 2,
+                    Some('"') => {
+                        i += 1;
+                        break;
+                    }
+                    _ => i += 1,
+                }
+            }
+            Token::Str
+        } else if c == '\'' && at(i + 1) == Some('\\') {
+            // An escaped char literal such as '\n' or '\u{1F4CE}'.
+            i += 2;
+            while i < chars.len() && at(i) != Some('\'') {
+                i += 1;
+            }
+            i += 1;
+            Token::Str
+        } else if c == '\'' && at(i + 2) == Some('\'') {
+            i += 3;
+            Token::Str
+        } else if c.is_ascii_digit() {
+            while at(i).is_some_and(|c| is_ident(c) || (c == '.' && at(i + 1) != Some('.'))) {
+                i += 1;
+            }
+            Token::Number
+        } else if is_ident(c) {
+            while at(i).is_some_and(is_ident) {
+                i += 1;
+            }
+            let word = &code[offset(start)..offset(i)];
+            if RUST_KEYWORDS.contains(&word) {
+                Token::Keyword
+            } else if word.starts_with(char::is_uppercase) {
+                Token::Type
+            } else {
+                Token::Plain
+            }
+        } else {
+            i += 1;
+            Token::Plain
+        };
+
+        let range = offset(start)..offset(i);
+        match tokens.last_mut() {
+            Some((last, Token::Plain)) if token == Token::Plain => last.end = range.end,
+            _ => tokens.push((range, token)),
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> Span {
+        Span { text: text.to_string(), ..Span::default() }
+    }
+
+    fn link(text: &str, url: &str) -> Span {
+        Span { link: Some(url.to_string()), ..plain(text) }
+    }
+
+    #[test]
+    fn snake_case_is_not_italic() {
+        assert_eq!(parse_inline("call snake_case_name now"), [plain("call snake_case_name now")]);
+        let spans = parse_inline("_really_ snake_case");
+        assert_eq!(spans, [Span { italic: true, ..plain("really") }, plain(" snake_case")]);
+    }
+
+    #[test]
+    fn unclosed_markers_stay_as_text() {
+        for text in ["a *b", "**bold", "`code", "[label](docs", "[label] (docs)"] {
+            assert_eq!(parse_inline(text), [plain(text)], "{text}");
+        }
+    }
+
+    #[test]
+    fn styles_and_escapes() {
+        assert_eq!(
+            parse_inline("**bold** and *it* \\*not\\*"),
+            [
+                Span { bold: true, ..plain("bold") },
+                plain(" and "),
+                Span { italic: true, ..plain("it") },
+                plain(" *not*"),
+            ]
+        );
+    }
+
+    #[test]
+    fn links_nest_in_styles_but_not_in_code() {
+        assert_eq!(
+            parse_inline("**see [docs](https://docs.rs)**"),
+            [
+                Span { bold: true, ..plain("see ") },
+                Span { bold: true, ..link("docs", "https://docs.rs") },
+            ]
+        );
+        assert_eq!(
+            parse_inline("`[a](https://x)` <https://y>"),
+            [
+                Span { code: true, ..plain("[a](https://x)") },
+                plain(" "),
+                link("https://y", "https://y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bare_urls_leave_trailing_punctuation() {
+        let url = "https://doc.rust-lang.org/book";
+        assert_eq!(
+            parse_inline(&format!("Read {url}.")),
+            [plain("Read "), link(url, url), plain(".")]
+        );
+    }
+
+    #[test]
+    fn only_web_and_mail_links_are_clickable() {
+        assert_eq!(
+            parse_inline("[mail](mailto:me@example.com)"),
+            [link("mail", "mailto:me@example.com")]
+        );
+        assert_eq!(parse_inline("http://x"), [link("http://x", "http://x")]);
+        assert_eq!(parse_inline("[x](http://x)"), [link("x", "http://x")]);
+        assert_eq!(parse_inline("mailto:me@example.com"), [plain("mailto:me@example.com")]);
+        let others = ["[x](file:///etc/passwd)", "[x](smb://host/share)", "[x](vscode://open)"];
+        for text in others {
+            assert!(parse_inline(text).iter().all(|span| span.link.is_none()), "{text}");
+        }
+        assert_eq!(parse_inline("[x](file:///etc/passwd)"), [plain("[x](file:///etc/passwd)")]);
+    }
+
+    #[test]
+    fn rust_tokens() {
+        let code = "fn f<'a>(s: &'a str) -> Option<u8> { let c = '\\n'; 4_2 } // \"done\"";
+        let tokens: Vec<(&str, Token)> =
+            highlight_rust(code).into_iter().map(|(range, token)| (&code[range], token)).collect();
+        let colored: Vec<(&str, Token)> =
+            tokens.iter().copied().filter(|(_, token)| *token != Token::Plain).collect();
+        assert_eq!(
+            colored,
+            [
+                ("fn", Token::Keyword),
+                ("Option", Token::Type),
+                ("let", Token::Keyword),
+                ("'\\n'", Token::Str),
+                ("4_2", Token::Number),
+                ("// \"done\"", Token::Comment),
+            ]
+        );
+        // Every byte is covered, in order.
+        assert_eq!(tokens.iter().map(|(text, _)| *text).collect::<String>(), code);
+    }
+
+    #[test]
+    fn unterminated_rust_literals_run_to_the_end() {
+        let code = "let s = \"open";
+        let tokens = highlight_rust(code);
+        assert_eq!(tokens.last(), Some(&(8..code.len(), Token::Str)));
+        let code = "x /* open";
+        assert_eq!(highlight_rust(code).last().map(|(_, token)| *token), Some(Token::Comment));
+    }
+
+    #[test]
+    fn blocks() {
+        let text = "Intro\nwraps\n\n- one\n  more\n- two\n```rust\nlet x;\n```";
+        let document = Document::parse(text);
+        assert_eq!(
+            document.blocks,
+            [
+                Block::Paragraph(vec![plain("Intro wraps")]),
+                Block::Bullet(vec![plain("one more")]),
+                Block::Bullet(vec![plain("two")]),
+                Block::Code { rust: true, text: "let x;".to_string() },
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! The bounds of the monitor under a point, for keeping balloons on screen.
+//!
+//! egui only reports the current monitor's size, which says nothing about
+//! where it sits once there is more than one. Returns `None` where the
+//! platform doesn't say, e.g. on Wayland, which doesn't tell windows where
+//! they are either.
+
+use eframe::egui;
+
+/// The monitor containing `point`, or the nearest one, in egui points in
+/// the same desktop coordinates as `ViewportInfo::inner_rect`. Monitors
+/// are measured with the window's own `pixels_per_point`, like everything
+/// else in that space, even where they have a scale factor of their own.
+pub fn bounds_at(point: egui::Pos2, pixels_per_point: f32) -> Option<egui::Rect> {
+    platform::bounds_at(point, pixels_per_point)
+}
+
+/// Picks the monitor containing `point`, or else the one closest to it.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn nearest(monitors: impl Iterator<Item = egui::Rect>, point: egui::Pos2) -> Option<egui::Rect> {
+    monitors.min_by(|a, b| a.distance_sq_to_pos(point).total_cmp(&b.distance_sq_to_pos(point)))
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use eframe::egui;
+    use std::os::raw::c_int;
+    use x11_dl::xlib::{Display, Xlib};
+    use x11_dl::xrandr::Xrandr;
+
+    struct Connection {
+        xlib: Xlib,
+        xrandr: Xrandr,
+        display: *mut Display,
+    }
+
+    thread_local! {
+        static CONNECTION: Option<Connection> = connect();
+    }
+
+    fn connect() -> Option<Connection> {
+        // XWayland's monitors don't line up with where Wayland puts windows.
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return None;
+        }
+
+        let xlib = Xlib::open().ok()?;
+        let xrandr = Xrandr::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        Some(Connection { xlib, xrandr, display })
+    }
+
+    /// Asks RandR every time, so plugging in a monitor is picked up.
+    pub fn bounds_at(point: egui::Pos2, pixels_per_point: f32) -> Option<egui::Rect> {
+        CONNECTION.with(|connection| {
+            let Connection { xlib, xrandr, display } = connection.as_ref()?;
+
+            let mut count: c_int = 0;
+            let monitors = unsafe {
+                let root = (xlib.XDefaultRootWindow)(*display);
+                (xrandr.XRRGetMonitors)(*display, root, 1, &mut count)
+            };
+            if monitors.is_null() {
+                return None;
+            }
+
+            let rects: Vec<egui::Rect> = unsafe {
+                std::slice::from_raw_parts(monitors, count.max(0) as usize)
+                    .iter()
+                    .map(|monitor| {
+                        let min = egui::pos2(monitor.x as f32, monitor.y as f32);
+                        let size = egui::vec2(monitor.width as f32, monitor.height as f32);
+                        egui::Rect::from_min_size(min, size) / pixels_per_point
+                    })
+                    .collect()
+            };
+            unsafe { (xrandr.XRRFreeMonitors)(monitors) };
+
+            super::nearest(rects.into_iter(), point)
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use eframe::egui;
+    use windows_sys::Win32::Foundation::POINT;
+    use windows_sys::Win32::Graphics::Gdi::{
+        GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST,
+    };
+
+    pub fn bounds_at(point: egui::Pos2, pixels_per_point: f32) -> Option<egui::Rect> {
+        let physical = point * pixels_per_point;
+        let point = POINT { x: physical.x as i32, y: physical.y as i32 };
+        let monitor = unsafe { MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST) };
+        if monitor.is_null() {
+            return None;
+        }
+
+        let mut info: MONITORINFO = unsafe { std::mem::zeroed() };
+        info.cbSize = std::mem::size_of::<MONITORINFO>() as u32;
+        if unsafe { GetMonitorInfoW(monitor, &mut info) } == 0 {
+            return None;
+        }
+        let rect = info.rcMonitor;
+        let min = egui::pos2(rect.left as f32, rect.top as f32);
+        let max = egui::pos2(rect.right as f32, rect.bottom as f32);
+        Some(egui::Rect::from_min_max(min, max) / pixels_per_point)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use core_graphics::display::CGDisplay;
+    use eframe::egui;
+
+    /// Quartz already reports points, so no scaling is needed.
+    pub fn bounds_at(point: egui::Pos2, _pixels_per_point: f32) -> Option<egui::Rect> {
+        let displays = CGDisplay::active_displays().ok()?;
+        let rects = displays.into_iter().map(|id| {
+            let bounds = CGDisplay::new(id).bounds();
+            let min = egui::pos2(bounds.origin.x as f32, bounds.origin.y as f32);
+            let size = egui::vec2(bounds.size.width as f32, bounds.size.height as f32);
+            egui::Rect::from_min_size(min, size)
+        });
+        super::nearest(rects, point)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows, target_os = "macos")))]
+mod platform {
+    use eframe::egui;
+
+    pub fn bounds_at(_point: egui::Pos2, _pixels_per_point: f32) -> Option<egui::Rect> {
+        None
+    }
+}
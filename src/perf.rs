@@ -0,0 +1,231 @@
+//! Numbers for the debug window: how long updates take, how often the
+//! animation, the GPU and the event loop are actually doing something, and
+//! how much memory that costs.
+
+use eframe::egui;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Samples of per-update timings kept for the update time graph.
+const FRAME_SAMPLES: usize = 120;
+/// Seconds of per-second rates kept for the rate graphs.
+const RATE_HISTORY: usize = 60;
+const GRAPH_SIZE: egui::Vec2 = egui::vec2(180.0, 28.0);
+
+/// Events in the last second, plus one sample per second for the graph.
+#[derive(Default)]
+struct Rate {
+    recent: VecDeque<Instant>,
+    history: VecDeque<f32>,
+}
+
+impl Rate {
+    fn record(&mut self, now: Instant) {
+        self.recent.push_back(now);
+    }
+
+    fn per_second(&mut self, now: Instant) -> usize {
+        while self.recent.front().is_some_and(|t| now - *t > Duration::from_secs(1)) {
+            self.recent.pop_front();
+        }
+        self.recent.len()
+    }
+
+    fn sample(&mut self, now: Instant) {
+        let rate = self.per_second(now) as f32;
+        push_capped(&mut self.history, rate, RATE_HISTORY);
+    }
+}
+
+fn push_capped(samples: &mut VecDeque<f32>, value: f32, cap: usize) {
+    if samples.len() == cap {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+pub struct PerfStats {
+    update_started: Option<Instant>,
+    /// Milliseconds spent in each `App::update`.
+    update_ms: VecDeque<f32>,
+    /// Microseconds per `extract_frame` call.
+    extract_us: VecDeque<f32>,
+    /// Microseconds per texture, from sheet pixels to an image handed to
+    /// egui, on either path.
+    upload_us: VecDeque<f32>,
+    repaints: Rate,
+    frame_changes: Rate,
+    uploads: Rate,
+    last_frame: Option<(u32, u32)>,
+    last_sample: Instant,
+    resident: Option<u64>,
+}
+
+impl PerfStats {
+    pub fn new() -> Self {
+        Self {
+            update_started: None,
+            update_ms: VecDeque::with_capacity(FRAME_SAMPLES),
+            extract_us: VecDeque::with_capacity(FRAME_SAMPLES),
+            upload_us: VecDeque::with_capacity(FRAME_SAMPLES),
+            repaints: Rate::default(),
+            frame_changes: Rate::default(),
+            uploads: Rate::default(),
+            last_frame: None,
+            last_sample: Instant::now(),
+            resident: resident_bytes(),
+        }
+    }
+
+    pub fn begin_update(&mut self) {
+        let now = Instant::now();
+        self.update_started = Some(now);
+        self.repaints.record(now);
+
+        if now - self.last_sample >= Duration::from_secs(1) {
+            self.last_sample = now;
+            self.repaints.sample(now);
+            self.frame_changes.sample(now);
+            self.uploads.sample(now);
+            self.resident = resident_bytes();
+        }
+    }
+
+    pub fn end_update(&mut self) {
+        if let Some(started) = self.update_started.take() {
+            let ms = started.elapsed().as_secs_f32() * 1000.0;
+            push_capped(&mut self.update_ms, ms, FRAME_SAMPLES);
+        }
+    }
+
+    /// Notes the sprite frame on screen, counting it when it changed.
+    pub fn shown(&mut self, frame: (u32, u32)) {
+        if self.last_frame != Some(frame) {
+            self.last_frame = Some(frame);
+            self.frame_changes.record(Instant::now());
+        }
+    }
+
+    /// Counts a texture upload that took `took` to prepare.
+    pub fn uploaded(&mut self, took: Duration) {
+        self.uploads.record(Instant::now());
+        push_capped(&mut self.upload_us, took.as_secs_f32() * 1e6, FRAME_SAMPLES);
+    }
+
+    pub fn extracted(&mut self, took: Duration) {
+        push_capped(&mut self.extract_us, took.as_secs_f32() * 1e6, FRAME_SAMPLES);
+    }
+
+    /// `target_fps` is the rate the animation is trying to run at right
+    /// now, zero while it rests.
+    pub fn show(&mut self, ui: &mut egui::Ui, target_fps: f32) {
+        let now = Instant::now();
+
+        let last_ms = self.update_ms.back().copied().unwrap_or(0.0);
+        ui.label(format!("Update time: {last_ms:.2} ms (max {:.2})", max(&self.update_ms)))
+            .on_hover_text("CPU time in our update; egui's painting and the GPU aren't counted.");
+        sparkline(ui, &self.update_ms);
+
+        let fps = self.frame_changes.per_second(now);
+        if target_fps > 0.0 {
+            ui.label(format!("Animation: {fps} fps (target {target_fps:.1})"));
+        } else {
+            ui.label(format!("Animation: {fps} fps (resting)"));
+        }
+        sparkline(ui, &self.frame_changes.history);
+
+        ui.label(format!("Repaints: {}/s", self.repaints.per_second(now)))
+            .on_hover_text("Includes the debug window's own refresh.");
+        sparkline(ui, &self.repaints.history);
+
+        ui.label(format!("Texture uploads: {}/s", self.uploads.per_second(now)));
+        sparkline(ui, &self.uploads.history);
+
+        let extract = match self.extract_us.back() {
+            Some(last) => format!("{last:.0} µs (max {:.0})", max(&self.extract_us)),
+            None => "not called, frames come from the atlas".to_string(),
+        };
+        ui.label(format!("extract_frame: {extract}"));
+
+        if let Some(last) = self.upload_us.back() {
+            ui.label(format!("Texture prep: {last:.0} µs (max {:.0})", max(&self.upload_us)))
+                .on_hover_text("Cropping, effects, upscaling and handing the image to egui.");
+        }
+
+        let resident = match self.resident {
+            Some(bytes) => format!("{:.1} MiB", bytes as f32 / (1024.0 * 1024.0)),
+            None => "unavailable".to_string(),
+        };
+        ui.label(format!("Resident memory: {resident}"));
+    }
+}
+
+fn max(samples: &VecDeque<f32>) -> f32 {
+    samples.iter().copied().fold(0.0, f32::max)
+}
+
+/// A small line graph scaled to its own maximum.
+fn sparkline(ui: &mut egui::Ui, samples: &VecDeque<f32>) {
+    let (rect, _) = ui.allocate_exact_size(GRAPH_SIZE, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+    let top = max(samples);
+    if samples.len() < 2 || top <= 0.0 {
+        return;
+    }
+
+    let step = rect.width() / (samples.len() - 1) as f32;
+    let points = samples
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            egui::pos2(rect.left() + i as f32 * step, rect.bottom() - value / top * rect.height())
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, ui.visuals().widgets.active.fg_stroke));
+}
+
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    // Second field: resident pages.
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(target_os = "macos")]
+fn resident_bytes() -> Option<u64> {
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+    let written = unsafe {
+        libc::proc_pidinfo(
+            libc::getpid(),
+            libc::PROC_PIDTASKINFO,
+            0,
+            &mut info as *mut _ as *mut libc::c_void,
+            size,
+        )
+    };
+    (written == size).then_some(info.pti_resident_size)
+}
+
+#[cfg(windows)]
+fn resident_bytes() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    counters.cb = size;
+    let ok = unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
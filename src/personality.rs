@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::manifest::ClipDef;
+#[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+use crate::manifest::Interaction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Personality {
+    #[default]
+    Calm,
+    Hyper,
+    Sarcastic,
+}
+
+impl Personality {
+    #[cfg(feature = "eframe")]
+    pub const ALL: [Personality; 3] = [
+        Personality::Calm,
+        Personality::Hyper,
+        Personality::Sarcastic,
+    ];
+
+    #[cfg(feature = "eframe")]
+    pub fn label(self) -> &'static str {
+        match self {
+            Personality::Calm => "Calm",
+            Personality::Hyper => "Hyper",
+            Personality::Sarcastic => "Sarcastic",
+        }
+    }
+
+    fn traits(self) -> Traits {
+        match self {
+            Personality::Calm => Traits {
+                resting_energy: 0.25,
+                boredom_per_sec: 0.004,
+                annoyance_per_poke: 0.08,
+                annoyance_decay_per_sec: 0.02,
+                cooldown_scale: 1.6,
+            },
+            Personality::Hyper => Traits {
+                resting_energy: 0.8,
+                boredom_per_sec: 0.02,
+                annoyance_per_poke: 0.05,
+                annoyance_decay_per_sec: 0.05,
+                cooldown_scale: 0.5,
+            },
+            Personality::Sarcastic => Traits {
+                resting_energy: 0.45,
+                boredom_per_sec: 0.01,
+                annoyance_per_poke: 0.25,
+                annoyance_decay_per_sec: 0.008,
+                cooldown_scale: 1.0,
+            },
+        }
+    }
+}
+
+struct Traits {
+    resting_energy: f32,
+    boredom_per_sec: f32,
+    /// Pokes only come from a window.
+    #[cfg_attr(
+        not(any(
+            feature = "eframe",
+            feature = "minifb",
+            feature = "pixels",
+            feature = "softbuffer"
+        )),
+        allow(dead_code)
+    )]
+    annoyance_per_poke: f32,
+    annoyance_decay_per_sec: f32,
+    cooldown_scale: f32,
+}
+
+/// How Clippy currently feels. Every value stays within `0.0..=1.0`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mood {
+    pub energy: f32,
+    pub boredom: f32,
+    pub annoyance: f32,
+}
+
+impl Mood {
+    pub fn new(personality: Personality) -> Self {
+        Self {
+            energy: personality.traits().resting_energy,
+            boredom: 0.0,
+            annoyance: 0.0,
+        }
+    }
+
+    /// Lets time pass: boredom builds up, annoyance wears off and energy
+    /// drifts back to the personality's resting level.
+    pub fn tick(&mut self, personality: Personality, secs: f32) {
+        let traits = personality.traits();
+
+        self.boredom += traits.boredom_per_sec * secs;
+        self.annoyance -= traits.annoyance_decay_per_sec * secs;
+        self.energy += (traits.resting_energy - self.energy) * (0.05 * secs).min(1.0);
+        self.clamp();
+    }
+
+    #[cfg(any(feature = "eframe", feature = "minifb", feature = "pixels", feature = "softbuffer"))]
+    pub fn on_interaction(&mut self, personality: Personality, interaction: Interaction) {
+        let traits = personality.traits();
+
+        match interaction {
+            Interaction::Click | Interaction::DoubleClick => {
+                self.annoyance += traits.annoyance_per_poke;
+                self.energy += 0.05;
+            }
+            Interaction::DragStart | Interaction::DragEnd => {
+                self.annoyance += traits.annoyance_per_poke * 0.5;
+                self.energy += 0.1;
+            }
+            Interaction::Hover => {}
+        }
+        self.boredom = 0.0;
+        self.clamp();
+    }
+
+    pub fn on_clip_finished(&mut self) {
+        self.boredom *= 0.5;
+        self.energy -= 0.02;
+        self.clamp();
+    }
+
+    /// Relative chance of picking `clip` from the idle pool, based on its tags.
+    pub fn clip_weight(&self, clip: &ClipDef) -> f32 {
+        let mut weight = 1.0;
+        for tag in &clip.tags {
+            weight += match tag.as_str() {
+                "calm" => 1.0 - self.energy,
+                "energetic" => self.energy * 2.0,
+                "attention" => self.boredom * 2.0,
+                "bored" => self.boredom,
+                "sassy" => self.annoyance * 3.0,
+                _ => 0.0,
+            };
+        }
+        weight
+    }
+
+    /// Multiplier for the delay between idle clips. Energetic or bored
+    /// moods act sooner.
+    pub fn cooldown_scale(&self, personality: Personality) -> f32 {
+        personality.traits().cooldown_scale * (1.5 - self.energy) * (1.0 - 0.5 * self.boredom)
+    }
+
+    fn clamp(&mut self) {
+        self.energy = self.energy.clamp(0.0, 1.0);
+        self.boredom = self.boredom.clamp(0.0, 1.0);
+        self.annoyance = self.annoyance.clamp(0.0, 1.0);
+    }
+}
@@ -0,0 +1,32 @@
+//! The sprite sheet's layout: every frame is a `FRAME_W` by `FRAME_H` cell,
+//! `FRAMES_PER_ROW` to a row. Also pulled into the benches by path, since
+//! they can't link against the binary.
+
+pub const FRAME_W: u32 = 124;
+pub const FRAME_H: u32 = 93;
+pub const FRAMES_PER_ROW: u32 = 27;
+
+pub fn load_sprite_sheet() -> image::RgbaImage {
+    let img_bytes = include_bytes!("../clippy_map.png");
+    image::load_from_memory(img_bytes)
+        .expect("Failed to load clippy sprite sheet")
+        .to_rgba8()
+}
+
+pub fn extract_frame(
+    sprite_sheet: &image::RgbaImage,
+    frame_x: u32,
+    frame_y: u32,
+) -> image::RgbaImage {
+    let src_x = frame_x * FRAME_W;
+    let src_y = frame_y * FRAME_H;
+
+    image::imageops::crop_imm(
+        sprite_sheet,
+        src_x,
+        src_y,
+        FRAME_W,
+        FRAME_H,
+    )
+        .to_image()
+}
@@ -0,0 +1,58 @@
+//! Checked-in snapshots for output that is easier to look at than to
+//! describe, kept in `snapshots/`. Run the tests with `UPDATE_SNAPSHOTS=1`
+//! to rewrite them after an intended change, then look over the diff.
+
+use image::RgbaImage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::{extract_frame, load_sprite_sheet, Animation};
+
+/// The sprite sheet, decoded once for every test that needs a frame.
+pub fn frame(linear: u32) -> RgbaImage {
+    static SHEET: OnceLock<RgbaImage> = OnceLock::new();
+    let sheet = SHEET.get_or_init(load_sprite_sheet);
+    let (frame_x, frame_y) = Animation::linear_to_grid(linear);
+    extract_frame(sheet, frame_x, frame_y)
+}
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join(name)
+}
+
+fn updating(path: &Path) -> bool {
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_none() {
+        return false;
+    }
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    true
+}
+
+fn missing(path: &Path, err: impl std::fmt::Display) -> ! {
+    panic!("{}: {err}; run with UPDATE_SNAPSHOTS=1 to create it", path.display())
+}
+
+/// Compares `actual` with the stored snapshot `name` byte for byte.
+pub fn assert_bytes(name: &str, actual: &[u8]) {
+    let path = path(name);
+    if updating(&path) {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read(&path).unwrap_or_else(|err| missing(&path, err));
+    assert!(expected == actual, "{name} no longer matches its snapshot");
+}
+
+/// Compares `actual` with the stored PNG `name` pixel for pixel, so a
+/// different encoder doesn't count as a change.
+pub fn assert_image(name: &str, actual: &RgbaImage) {
+    let path = path(name);
+    if updating(&path) {
+        actual.save(&path).unwrap();
+        return;
+    }
+    let expected = image::open(&path).unwrap_or_else(|err| missing(&path, err)).to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{name} changed size");
+    assert!(expected == *actual, "{name} no longer matches its snapshot");
+}
@@ -0,0 +1,493 @@
+//! Terminal front end: the same `Animation` drawn into the bottom-right
+//! corner of a terminal pane, for SSH sessions and tmux.
+//!
+//! Frames are drawn with Unicode half blocks in truecolor, or with the kitty
+//! graphics protocol or sixel where the terminal supports them. Rendering a
+//! frame is a pure function of the frame and its size, so `--once` output
+//! can be compared against a stored snapshot, as the tests do.
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+
+use crate::manifest::ClipManifest;
+use crate::personality::Personality;
+use crate::upscale;
+use crate::{extract_frame, load_sprite_sheet, Animation, FRAME_H, FRAME_W};
+
+const DEFAULT_WIDTH: u32 = 32;
+const MAX_SLEEP_MS: u64 = 200;
+const ALPHA_CUTOFF: u8 = 128;
+const KITTY_IMAGE_ID: u32 = 7231;
+const KITTY_CHUNK: usize = 4096;
+/// Cell height in pixels to assume when the terminal doesn't report one.
+const FALLBACK_CELL_HEIGHT: u32 = 16;
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Graphics {
+    Blocks,
+    Kitty,
+    Sixel,
+}
+
+impl Graphics {
+    /// Picks the richest protocol the terminal is known to handle. tmux
+    /// swallows graphics escapes unless specially configured, so it always
+    /// gets half blocks.
+    fn detect() -> Self {
+        let env = |name| std::env::var(name).unwrap_or_default();
+
+        if !env("TMUX").is_empty() {
+            return Graphics::Blocks;
+        }
+        if !env("KITTY_WINDOW_ID").is_empty() || env("TERM") == "xterm-kitty" {
+            return Graphics::Kitty;
+        }
+        let term = env("TERM");
+        if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            return Graphics::Sixel;
+        }
+        Graphics::Blocks
+    }
+
+    fn parse(value: &str) -> Result<Option<Self>, String> {
+        match value {
+            "auto" => Ok(None),
+            "blocks" => Ok(Some(Graphics::Blocks)),
+            "kitty" => Ok(Some(Graphics::Kitty)),
+            "sixel" => Ok(Some(Graphics::Sixel)),
+            other => Err(format!("unknown graphics mode `{other}`")),
+        }
+    }
+}
+
+struct Options {
+    width: u32,
+    graphics: Graphics,
+    once: bool,
+    frame: Option<u32>,
+}
+
+const USAGE: &str = "usage: tiny-clippy terminal [--width CELLS] \
+[--graphics auto|blocks|kitty|sixel] [--once [--frame N]]";
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            width: DEFAULT_WIDTH,
+            graphics: Graphics::detect(),
+            once: false,
+            frame: None,
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                "--width" => {
+                    options.width = value("--width")?
+                        .parse()
+                        .map_err(|_| "--width must be a number of cells".to_string())?;
+                }
+                "--graphics" => {
+                    if let Some(graphics) = Graphics::parse(&value("--graphics")?)? {
+                        options.graphics = graphics;
+                    }
+                }
+                "--once" => options.once = true,
+                "--frame" => {
+                    options.frame = Some(
+                        value("--frame")?
+                            .parse()
+                            .map_err(|_| "--frame must be a frame number".to_string())?,
+                    );
+                }
+                "--help" | "-h" => return Err(USAGE.to_string()),
+                other => return Err(format!("unknown option `{other}`\n{USAGE}")),
+            }
+        }
+
+        if options.width == 0 {
+            return Err("--width must be at least 1".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Where a frame goes on screen, as 1-based terminal row and column.
+/// `None` writes it inline instead.
+type Origin = Option<(u32, u32)>;
+
+/// Size a frame takes up in cells for `width` columns.
+fn cell_size(graphics: Graphics, width: u32, cell_height_px: u32) -> (u32, u32) {
+    match graphics {
+        // Cells are roughly twice as tall as wide, the same ratio half
+        // blocks draw at.
+        Graphics::Blocks | Graphics::Kitty => (width, (FRAME_H * width).div_ceil(FRAME_W * 2)),
+        // Sixel draws at native size, however many rows that covers.
+        Graphics::Sixel => (FRAME_W.div_ceil(8), FRAME_H.div_ceil(cell_height_px.max(1))),
+    }
+}
+
+/// Renders one frame as terminal output.
+pub fn render_frame(frame: &RgbaImage, graphics: Graphics, width: u32, origin: Origin) -> String {
+    match graphics {
+        Graphics::Blocks => render_blocks(frame, width, origin),
+        Graphics::Kitty => render_kitty(frame, cell_size(graphics, width, 0), origin),
+        Graphics::Sixel => render_sixel(frame, origin),
+    }
+}
+
+fn move_to(out: &mut String, origin: Origin, line: u32) {
+    if let Some((row, col)) = origin {
+        let _ = write!(out, "\x1b[{};{}H", row + line, col);
+    }
+}
+
+fn render_blocks(frame: &RgbaImage, width: u32, origin: Origin) -> String {
+    let (cols, rows) = cell_size(Graphics::Blocks, width, 0);
+    // Premultiplied, so the magenta under transparent pixels stays hidden.
+    let scaled = upscale::resize_smooth(frame, cols, rows * 2);
+
+    let mut out = String::new();
+    for line in 0..rows {
+        move_to(&mut out, origin, line);
+        for x in 0..cols {
+            let top = scaled.get_pixel(x, line * 2);
+            let bottom = scaled.get_pixel(x, line * 2 + 1);
+            let (top_on, bottom_on) = (top[3] >= ALPHA_CUTOFF, bottom[3] >= ALPHA_CUTOFF);
+
+            match (top_on, bottom_on) {
+                (false, false) => out.push_str("\x1b[0m "),
+                (true, false) => {
+                    let _ = write!(out, "\x1b[0;38;2;{};{};{}m\u{2580}", top[0], top[1], top[2]);
+                }
+                (false, true) => {
+                    let _ = write!(
+                        out,
+                        "\x1b[0;38;2;{};{};{}m\u{2584}",
+                        bottom[0], bottom[1], bottom[2]
+                    );
+                }
+                (true, true) => {
+                    let _ = write!(
+                        out,
+                        "\x1b[38;2;{};{};{};48;2;{};{};{}m\u{2580}",
+                        top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                    );
+                }
+            }
+        }
+        out.push_str("\x1b[0m");
+        if origin.is_none() {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn render_kitty(frame: &RgbaImage, (cols, rows): (u32, u32), origin: Origin) -> String {
+    let mut out = String::new();
+    // Drop the previous frame, data included, before placing the new one.
+    let _ = write!(out, "\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\");
+    move_to(&mut out, origin, 0);
+
+    let payload = base64(frame.as_raw());
+    let chunks: Vec<&str> = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ASCII"))
+        .collect();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(index + 1 < chunks.len());
+        if index == 0 {
+            let (width, height) = frame.dimensions();
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=32,s={width},v={height},c={cols},r={rows},i={KITTY_IMAGE_ID},C=1,q=2"
+            );
+            let _ = write!(out, ",m={more};{chunk}\x1b\\");
+        } else {
+            let _ = write!(out, "\x1b_Gm={more};{chunk}\x1b\\");
+        }
+    }
+    if origin.is_none() {
+        out.push('\n');
+    }
+    out
+}
+
+/// Index into a 6x6x6 color cube, or `None` for transparent pixels.
+fn sixel_color(pixel: &image::Rgba<u8>) -> Option<usize> {
+    if pixel[3] < ALPHA_CUTOFF {
+        return None;
+    }
+    let level = |c: u8| (c as usize * 5 + 127) / 255;
+    Some(level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]))
+}
+
+fn push_sixel_run(out: &mut String, sixel: char, run: usize) {
+    if run > 3 {
+        let _ = write!(out, "!{run}{sixel}");
+    } else {
+        out.extend(std::iter::repeat_n(sixel, run));
+    }
+}
+
+fn render_sixel(frame: &RgbaImage, origin: Origin) -> String {
+    let (width, height) = frame.dimensions();
+    let mut out = String::new();
+    move_to(&mut out, origin, 0);
+
+    // P2 = 1 leaves unpainted pixels transparent.
+    let _ = write!(out, "\x1bP0;1;0q\"1;1;{width};{height}");
+
+    let mut used = [false; 216];
+    for pixel in frame.pixels() {
+        if let Some(color) = sixel_color(pixel) {
+            used[color] = true;
+        }
+    }
+    for (color, _) in used.iter().enumerate().filter(|(_, used)| **used) {
+        let percent = |level: usize| level * 100 / 5;
+        let _ = write!(
+            out,
+            "#{color};2;{};{};{}",
+            percent(color / 36),
+            percent(color / 6 % 6),
+            percent(color % 6)
+        );
+    }
+
+    for band in (0..height).step_by(6) {
+        let band_rows = (band..(band + 6).min(height)).collect::<Vec<_>>();
+        let mut colors: Vec<usize> = band_rows
+            .iter()
+            .flat_map(|&y| (0..width).filter_map(move |x| sixel_color(frame.get_pixel(x, y))))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (index, &color) in colors.iter().enumerate() {
+            if index > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{color}");
+
+            let mut current = None;
+            let mut run = 0;
+            for x in 0..width {
+                let mut bits = 0u8;
+                for (bit, &y) in band_rows.iter().enumerate() {
+                    if sixel_color(frame.get_pixel(x, y)) == Some(color) {
+                        bits |= 1 << bit;
+                    }
+                }
+                let sixel = (63 + bits) as char;
+                if current == Some(sixel) {
+                    run += 1;
+                } else {
+                    if let Some(previous) = current {
+                        push_sixel_run(&mut out, previous, run);
+                    }
+                    current = Some(sixel);
+                    run = 1;
+                }
+            }
+            if let Some(previous) = current {
+                push_sixel_run(&mut out, previous, run);
+            }
+        }
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+    if origin.is_none() {
+        out.push('\n');
+    }
+    out
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Terminal columns and rows, and the height of one cell in pixels.
+type TermSize = (u32, u32, u32);
+
+#[cfg(unix)]
+fn terminal_size() -> TermSize {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    if !ok || size.ws_col == 0 || size.ws_row == 0 {
+        return (80, 24, FALLBACK_CELL_HEIGHT);
+    }
+
+    let cell_height = match size.ws_ypixel {
+        0 => FALLBACK_CELL_HEIGHT,
+        pixels => pixels as u32 / size.ws_row as u32,
+    };
+    (size.ws_col as u32, size.ws_row as u32, cell_height)
+}
+
+#[cfg(not(unix))]
+fn terminal_size() -> TermSize {
+    let env = |name| std::env::var(name).ok().and_then(|value| value.parse().ok());
+    (env("COLUMNS").unwrap_or(80), env("LINES").unwrap_or(24), FALLBACK_CELL_HEIGHT)
+}
+
+#[cfg(unix)]
+fn install_stop_handler() {
+    extern "C" fn on_signal(_: libc::c_int) {
+        STOP.store(true, Ordering::SeqCst);
+    }
+
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_stop_handler() {}
+
+fn clear_area(out: &mut String, origin: (u32, u32), (cols, rows): (u32, u32)) {
+    for line in 0..rows {
+        move_to(out, Some(origin), line);
+        let _ = write!(out, "\x1b[0m{}", " ".repeat(cols as usize));
+    }
+}
+
+pub fn run(args: impl Iterator<Item = String>) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let sprite_sheet = load_sprite_sheet();
+    let mut stdout = std::io::stdout().lock();
+
+    if options.once {
+        let frame = options.frame.unwrap_or(0);
+        let frames = (sprite_sheet.width() / FRAME_W) * (sprite_sheet.height() / FRAME_H);
+        if frame >= frames {
+            return Err(format!("frame {frame} is past the end of the sprite sheet"));
+        }
+        let (frame_x, frame_y) = Animation::linear_to_grid(frame);
+        let image = extract_frame(&sprite_sheet, frame_x, frame_y);
+        let output = render_frame(&image, options.graphics, options.width, None);
+        return stdout.write_all(output.as_bytes()).map_err(|err| err.to_string());
+    }
+
+    install_stop_handler();
+    let mut animation = Animation::new(ClipManifest::embedded(), Personality::Calm);
+    let mut drawn: Option<((u32, u32), TermSize)> = None;
+    let mut placed: Option<((u32, u32), (u32, u32))> = None;
+
+    let _ = write!(stdout, "\x1b[?25l");
+    while !STOP.load(Ordering::SeqCst) {
+        let coords = animation.update();
+        let size = terminal_size();
+
+        if drawn != Some((coords, size)) {
+            let (term_cols, term_rows, cell_height) = size;
+            let cells = cell_size(options.graphics, options.width, cell_height);
+            let origin = (
+                term_rows.saturating_sub(cells.1).max(1),
+                term_cols.saturating_sub(cells.0).max(1),
+            );
+
+            let mut output = String::new();
+            if let Some((old_origin, old_cells)) = placed {
+                if old_origin != origin || old_cells != cells {
+                    clear_area(&mut output, old_origin, old_cells);
+                }
+            }
+            if options.graphics == Graphics::Sixel {
+                // Sixel keeps transparent pixels, so wipe the old frame first.
+                clear_area(&mut output, origin, cells);
+            }
+
+            let image = extract_frame(&sprite_sheet, coords.0, coords.1);
+            output.push_str(&render_frame(&image, options.graphics, options.width, Some(origin)));
+            stdout.write_all(output.as_bytes()).map_err(|err| err.to_string())?;
+            stdout.flush().map_err(|err| err.to_string())?;
+
+            drawn = Some((coords, size));
+            placed = Some((origin, cells));
+        }
+
+        let until_change = animation.next_change().saturating_duration_since(Instant::now());
+        std::thread::sleep(until_change.min(Duration::from_millis(MAX_SLEEP_MS)));
+    }
+
+    let mut output = String::new();
+    if options.graphics == Graphics::Kitty {
+        let _ = write!(output, "\x1b_Ga=d,d=I,i={KITTY_IMAGE_ID},q=2\x1b\\");
+    }
+    if let Some((origin, cells)) = placed {
+        clear_area(&mut output, origin, cells);
+    }
+    output.push_str("\x1b[0m\x1b[?25h");
+    stdout.write_all(output.as_bytes()).map_err(|err| err.to_string())?;
+    stdout.flush().map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{self, frame};
+
+    #[test]
+    fn blocks() {
+        for (linear, width) in [(0, 32), (0, 8), (60, 32)] {
+            let output = render_frame(&frame(linear), Graphics::Blocks, width, None);
+            let name = format!("terminal-blocks-{linear}-w{width}.txt");
+            snapshot::assert_bytes(&name, output.as_bytes());
+        }
+    }
+
+    #[test]
+    fn blocks_at_origin() {
+        let output = render_frame(&frame(0), Graphics::Blocks, 8, Some((3, 70)));
+        snapshot::assert_bytes("terminal-blocks-0-w8-placed.txt", output.as_bytes());
+    }
+
+    #[test]
+    fn kitty() {
+        let output = render_frame(&frame(0), Graphics::Kitty, 32, Some((1, 1)));
+        snapshot::assert_bytes("terminal-kitty-0.txt", output.as_bytes());
+    }
+
+    #[test]
+    fn sixel() {
+        for linear in [0, 60] {
+            let output = render_frame(&frame(linear), Graphics::Sixel, 32, None);
+            snapshot::assert_bytes(&format!("terminal-sixel-{linear}.txt"), output.as_bytes());
+        }
+    }
+
+    #[test]
+    fn base64_pads() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,403 @@
+//! Tips of the day: short Markdown snippets Clippy brings up between idle
+//! clips. They come from the embedded `tips.toml` and any TOML or Markdown
+//! files in a `tips` folder next to the settings, and rotate so none
+//! repeats until every other one has been shown.
+//!
+//! What the user asked to see less of lives in its own small file, read
+//! before and written after every change, so `tiny-clippy tip` and the
+//! Clippy on the desktop don't undo each other's answers.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const TIPS_DIR: &str = "tips";
+const STATE_FILE: &str = "tip-state.toml";
+/// Each "Fewer like this" on a tag divides the odds of its tips by four.
+const FEWER_WEIGHT: f32 = 0.25;
+/// Keeps heavily damped tags from rounding down to a weight of zero.
+const MAX_FEWER: u32 = 10;
+/// A Markdown tip file separates tips with a line holding just this.
+const MARKDOWN_SEPARATOR: &str = "---";
+/// An optional first line of a Markdown tip: `tags: rust, iterators`.
+const MARKDOWN_TAGS: &str = "tags:";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tip {
+    /// Stable name for "Don't show again". Derived from the text when a
+    /// file doesn't give one.
+    #[serde(default)]
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TipFile {
+    #[serde(default)]
+    tip: Vec<Tip>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TipState {
+    /// Ids of tips never to show again.
+    hidden: BTreeSet<String>,
+    /// How many times each tag was asked to come up less.
+    fewer: BTreeMap<String, u32>,
+    /// Ids shown since the rotation last started over.
+    seen: BTreeSet<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last: Option<String>,
+}
+
+pub struct Tips {
+    tips: Vec<Tip>,
+    /// Every tag any tip has, sorted, for picking a topic.
+    tags: Vec<String>,
+    /// `None` where the platform has no data directory; answers then only
+    /// last as long as Clippy does.
+    state_path: Option<PathBuf>,
+    state: TipState,
+}
+
+impl Tips {
+    /// The embedded tips plus any in the data directory. Files that fail to
+    /// parse are reported and skipped.
+    pub fn load(app_id: &str) -> Self {
+        let mut tips = parse_toml(include_str!("../tips.toml"), None)
+            .expect("Failed to parse the embedded tips");
+
+        let data_dir = eframe::storage_dir(app_id);
+        if let Some(dir) = &data_dir {
+            tips.extend(load_dir(&dir.join(TIPS_DIR)));
+        }
+
+        let mut tags: Vec<String> = tips.iter().flat_map(|tip| tip.tags.clone()).collect();
+        tags.sort();
+        tags.dedup();
+
+        let mut loaded = Self {
+            tips,
+            tags,
+            state_path: data_dir.map(|dir| dir.join(STATE_FILE)),
+            state: TipState::default(),
+        };
+        loaded.reload_state();
+        loaded
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The next tip, tagged `tag` if one is given. Tips already shown this
+    /// rotation are skipped, and once none are left it starts over without
+    /// repeating the last one. Tips on a topic asked to come up less sit
+    /// out some rotations, so they are seen less often rather than just
+    /// later. `None` when every tip there is was hidden.
+    pub fn pick(&mut self, tag: Option<&str>) -> Option<Tip> {
+        self.reload_state();
+        let state = &mut self.state;
+        let mut rng = rand::thread_rng();
+
+        let wanted: Vec<&Tip> = self
+            .tips
+            .iter()
+            .filter(|tip| !state.hidden.contains(&tip.id))
+            .filter(|tip| tag.is_none_or(|tag| tip.tags.iter().any(|t| t == tag)))
+            .collect();
+        if wanted.is_empty() {
+            return None;
+        }
+
+        let mut fresh: Vec<&Tip> =
+            wanted.iter().copied().filter(|tip| !state.seen.contains(&tip.id)).collect();
+        let mut started_over = false;
+        let tip = loop {
+            if fresh.is_empty() && started_over {
+                // Every tip sat out two rotations in a row; just go by the odds.
+                break *wanted.choose_weighted(&mut rng, |tip| weight(state, tip)).ok()?;
+            }
+            if fresh.is_empty() {
+                for tip in &wanted {
+                    state.seen.remove(&tip.id);
+                }
+                fresh = wanted
+                    .iter()
+                    .copied()
+                    .filter(|tip| wanted.len() == 1 || state.last.as_ref() != Some(&tip.id))
+                    .collect();
+                started_over = true;
+            }
+
+            let tip = fresh.swap_remove(rng.gen_range(0..fresh.len()));
+            if rng.gen::<f32>() < weight(state, tip) {
+                break tip;
+            }
+            state.seen.insert(tip.id.clone());
+        };
+
+        let tip = tip.clone();
+        state.seen.insert(tip.id.clone());
+        state.last = Some(tip.id.clone());
+        self.save_state();
+        Some(tip)
+    }
+
+    pub fn never_show(&mut self, tip: &Tip) {
+        self.reload_state();
+        self.state.hidden.insert(tip.id.clone());
+        self.save_state();
+    }
+
+    /// Makes every tip sharing a tag with `tip` come up less often.
+    pub fn show_fewer_like(&mut self, tip: &Tip) {
+        self.reload_state();
+        for tag in &tip.tags {
+            let fewer = self.state.fewer.entry(tag.clone()).or_insert(0);
+            *fewer = (*fewer + 1).min(MAX_FEWER);
+        }
+        self.save_state();
+    }
+
+    /// Forgets every "Don't show again" and "Fewer like this".
+    pub fn reset(&mut self) {
+        self.reload_state();
+        self.state.hidden.clear();
+        self.state.fewer.clear();
+        self.save_state();
+    }
+
+    fn reload_state(&mut self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        match fs::read_to_string(path) {
+            Ok(text) => match toml::from_str(&text) {
+                Ok(state) => self.state = state,
+                Err(err) => eprintln!("tiny-clippy: failed to parse {}: {err}", path.display()),
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => eprintln!("tiny-clippy: failed to read {}: {err}", path.display()),
+        }
+    }
+
+    fn save_state(&self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let saved = toml::to_string(&self.state).map_err(io::Error::other).and_then(|text| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, text)
+        });
+        if let Err(err) = saved {
+            eprintln!("tiny-clippy: failed to save {}: {err}", path.display());
+        }
+    }
+}
+
+fn weight(state: &TipState, tip: &Tip) -> f32 {
+    tip.tags
+        .iter()
+        .filter_map(|tag| state.fewer.get(tag))
+        .map(|&fewer| FEWER_WEIGHT.powi(fewer as i32))
+        .product()
+}
+
+/// Every `.toml` and `.md` file in `dir`, each tip also tagged with its
+/// file's name so a whole file can be asked for as a topic.
+fn load_dir(dir: &Path) -> Vec<Tip> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries.filter_map(|entry| Some(entry.ok()?.path())).collect();
+    paths.sort();
+
+    let mut tips = Vec::new();
+    for path in paths {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let parsed = match extension {
+            Some("toml") => fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| parse_toml(&text, Some(stem))),
+            Some("md") => fs::read_to_string(&path)
+                .map(|text| parse_markdown(&text, stem))
+                .map_err(|err| err.to_string()),
+            _ => continue,
+        };
+        match parsed {
+            Ok(parsed) => tips.extend(parsed),
+            Err(err) => eprintln!("tiny-clippy: skipping {}: {err}", path.display()),
+        }
+    }
+    tips
+}
+
+fn parse_toml(text: &str, file_tag: Option<&str>) -> Result<Vec<Tip>, String> {
+    let file: TipFile = toml::from_str(text).map_err(|err| err.to_string())?;
+    Ok(file.tip.into_iter().map(|tip| finish(tip, file_tag)).collect())
+}
+
+fn parse_markdown(text: &str, file_tag: &str) -> Vec<Tip> {
+    let mut sections = vec![Vec::new()];
+    for line in text.lines() {
+        if line.trim() == MARKDOWN_SEPARATOR {
+            sections.push(Vec::new());
+        } else if let Some(section) = sections.last_mut() {
+            section.push(line);
+        }
+    }
+
+    sections
+        .into_iter()
+        .filter_map(|lines| {
+            let mut lines = lines.into_iter().skip_while(|line| line.trim().is_empty()).peekable();
+            let tags = lines
+                .next_if(|line| line.to_lowercase().starts_with(MARKDOWN_TAGS))
+                .map(|line| {
+                    line[MARKDOWN_TAGS.len()..]
+                        .split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let text = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+            let tip = Tip { id: String::new(), text, tags };
+            (!tip.text.is_empty()).then(|| finish(tip, Some(file_tag)))
+        })
+        .collect()
+}
+
+/// Fills in the id and adds the file's tag.
+fn finish(mut tip: Tip, file_tag: Option<&str>) -> Tip {
+    if tip.id.is_empty() {
+        tip.id = format!("{:016x}", fnv1a(&tip.text));
+    }
+    if let Some(tag) = file_tag.filter(|tag| !tip.tags.iter().any(|t| t == tag)) {
+        tip.tags.push(tag.to_string());
+    }
+    tip
+}
+
+/// A hash that stays the same across builds and platforms, unlike std's.
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tips kept only in memory, each tagged with the tag given for it.
+    fn tips(tags: &[&str]) -> Tips {
+        let tips: Vec<Tip> = tags
+            .iter()
+            .enumerate()
+            .map(|(index, tag)| {
+                let tip = Tip { id: String::new(), text: format!("Tip {index}"), tags: Vec::new() };
+                finish(tip, Some(tag))
+            })
+            .collect();
+        let tags = tags.iter().map(|tag| tag.to_string()).collect();
+        Tips { tips, tags, state_path: None, state: TipState::default() }
+    }
+
+    fn picks(tips: &mut Tips, count: usize) -> Vec<String> {
+        (0..count).map(|_| tips.pick(None).unwrap().text).collect()
+    }
+
+    #[test]
+    fn every_tip_comes_up_before_any_repeats() {
+        let mut tips = tips(&["a"; 5]);
+        let mut first = picks(&mut tips, 5);
+        first.sort();
+        first.dedup();
+        assert_eq!(first.len(), 5);
+
+        let picks = picks(&mut tips, 200);
+        assert!(picks.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    #[test]
+    fn fewer_like_this_makes_a_tag_rarer() {
+        let mut tips = tips(&["a", "a", "a", "b", "b", "b"]);
+        let tip = tips.tips[0].clone();
+        tips.show_fewer_like(&tip);
+        tips.show_fewer_like(&tip);
+
+        let picks = picks(&mut tips, 1200);
+        let rare = picks.iter().filter(|text| ["Tip 0", "Tip 1", "Tip 2"].contains(&text.as_str()));
+        let rare = rare.count();
+        assert!(rare > 0 && rare < (picks.len() - rare) / 3, "{rare} of {}", picks.len());
+    }
+
+    #[test]
+    fn hidden_tips_never_come_back() {
+        let mut tips = tips(&["a", "a", "b"]);
+        let hidden = tips.tips[0].clone();
+        tips.never_show(&hidden);
+        assert!(picks(&mut tips, 100).iter().all(|text| *text != hidden.text));
+        assert!(tips.pick(Some("b")).is_some());
+
+        for tip in tips.tips.clone() {
+            tips.never_show(&tip);
+        }
+        assert!(tips.pick(None).is_none());
+        tips.reset();
+        assert!(tips.pick(None).is_some());
+    }
+
+    #[test]
+    fn markdown_sections_become_tips() {
+        let text = "\
+Use `cargo clippy`.
+---
+
+tags: Rust, lints , ,onboarding
+Run it with `-D warnings`
+in CI.
+---
+---
+tags: only tags
+";
+        let parsed = parse_markdown(text, "onboarding");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].text, "Use `cargo clippy`.");
+        assert_eq!(parsed[0].tags, ["onboarding"]);
+        assert_eq!(parsed[1].text, "Run it with `-D warnings`\nin CI.");
+        assert_eq!(parsed[1].tags, ["Rust", "lints", "onboarding"]);
+        assert_eq!(parsed[0].id, format!("{:016x}", fnv1a(&parsed[0].text)));
+        assert_ne!(parsed[0].id, parsed[1].id);
+    }
+
+    #[test]
+    fn toml_tips_keep_their_ids() {
+        let text = r#"
+[[tip]]
+id = "named"
+text = "One"
+
+[[tip]]
+text = "Two"
+tags = ["x"]
+"#;
+        let parsed = parse_toml(text, Some("file")).unwrap();
+        assert_eq!(parsed[0].id, "named");
+        assert_eq!(parsed[0].tags, ["file"]);
+        assert_eq!(parsed[1].id, format!("{:016x}", fnv1a("Two")));
+        assert_eq!(parsed[1].tags, ["x", "file"]);
+        assert!(parse_toml("[[tip]]\nid = 3", None).is_err());
+    }
+}
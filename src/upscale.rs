@@ -0,0 +1,455 @@
+//! Pixel-art upscalers run on the CPU when a frame is cached, so the GPU
+//! only has to show a texture that is already close to screen size.
+//!
+//! Pixels are compared in YUV with alpha, so fully transparent pixels all
+//! count as the same color whatever RGB they carry.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+/// YUV distance under which two colors count as the same, per hqx.
+const SIMILAR_Y: f32 = 48.0;
+const SIMILAR_U: f32 = 7.0;
+const SIMILAR_V: f32 = 6.0;
+const SIMILAR_A: f32 = 32.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Upscale {
+    /// Plain pixel repetition.
+    #[default]
+    Nearest,
+    Scale2x,
+    Scale3x,
+    /// A light hq2x-style blend; see `smooth2x_corner`.
+    #[serde(alias = "Hq2x")]
+    Smooth2x,
+    /// 2xBR: finds diagonal edges from a 5x5 neighbourhood and blends
+    /// across them.
+    Xbr,
+    /// Bilinear sampling on the GPU, for zoom levels that aren't whole.
+    Linear,
+}
+
+impl Upscale {
+    pub const ALL: [Upscale; 6] = [
+        Upscale::Nearest,
+        Upscale::Scale2x,
+        Upscale::Scale3x,
+        Upscale::Smooth2x,
+        Upscale::Xbr,
+        Upscale::Linear,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Upscale::Nearest => "Nearest",
+            Upscale::Scale2x => "Scale2x",
+            Upscale::Scale3x => "Scale3x",
+            Upscale::Smooth2x => "smooth2x",
+            Upscale::Xbr => "xBR",
+            Upscale::Linear => "Linear",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|upscale| upscale.label().eq_ignore_ascii_case(name))
+    }
+
+    /// How many times larger `apply` makes a frame.
+    pub fn factor(self) -> u32 {
+        match self {
+            Upscale::Nearest | Upscale::Linear => 1,
+            Upscale::Scale2x | Upscale::Smooth2x | Upscale::Xbr => 2,
+            Upscale::Scale3x => 3,
+        }
+    }
+
+    /// Whether the result should be sampled smoothly rather than by
+    /// repeating pixels when it still doesn't match the screen size.
+    pub fn smooth(self) -> bool {
+        self != Upscale::Nearest
+    }
+}
+
+pub fn apply(upscale: Upscale, frame: &RgbaImage) -> RgbaImage {
+    match upscale {
+        Upscale::Nearest | Upscale::Linear => frame.clone(),
+        Upscale::Scale2x => scale2x(frame),
+        Upscale::Scale3x => scale3x(frame),
+        Upscale::Smooth2x => by_corner(frame, smooth2x_corner),
+        Upscale::Xbr => by_corner(frame, xbr_corner),
+    }
+}
+
+/// Runs `upscale` on a frame, then resamples whatever is left to reach
+/// `scale` times its size, so the result can be shown pixel for pixel. A
+/// filter that would overshoot `scale` is skipped, since shrinking its
+/// output back down only blurs it.
+pub fn to_scale(upscale: Upscale, frame: &RgbaImage, scale: u32) -> RgbaImage {
+    let image = if upscale.factor() <= scale {
+        apply(upscale, frame)
+    } else {
+        frame.clone()
+    };
+    let (width, height) = (frame.width() * scale, frame.height() * scale);
+    if image.dimensions() == (width, height) {
+        image
+    } else if upscale.smooth() {
+        resize_smooth(&image, width, height)
+    } else {
+        imageops::resize(&image, width, height, FilterType::Nearest)
+    }
+}
+
+/// Bilinear resize in premultiplied alpha, as the GPU samples it, so the
+/// colors hidden under transparent pixels don't fringe the edges.
+pub fn resize_smooth(frame: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let mut premultiplied = frame.clone();
+    for pixel in premultiplied.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as u32 * alpha / 255) as u8;
+        }
+    }
+
+    let mut resized = imageops::resize(&premultiplied, width, height, FilterType::Triangle);
+    for pixel in resized.pixels_mut() {
+        let alpha = pixel[3] as u32;
+        for c in 0..3 {
+            let unmultiplied = (pixel[c] as u32 * 255).checked_div(alpha).unwrap_or(0);
+            pixel[c] = unmultiplied.min(255) as u8;
+        }
+    }
+    resized
+}
+
+/// Transparent pixels are all alike, whatever color they hide.
+fn normalized(pixel: Rgba<u8>) -> Rgba<u8> {
+    if pixel[3] == 0 {
+        Rgba([0, 0, 0, 0])
+    } else {
+        pixel
+    }
+}
+
+fn at(frame: &RgbaImage, x: i64, y: i64) -> Rgba<u8> {
+    let x = x.clamp(0, frame.width() as i64 - 1) as u32;
+    let y = y.clamp(0, frame.height() as i64 - 1) as u32;
+    normalized(*frame.get_pixel(x, y))
+}
+
+fn yuv(pixel: Rgba<u8>) -> [f32; 4] {
+    let [r, g, b, a] = pixel.0.map(|c| c as f32);
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        -0.169 * r - 0.331 * g + 0.5 * b,
+        0.5 * r - 0.419 * g - 0.081 * b,
+        a,
+    ]
+}
+
+fn similar(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    let (a, b) = (yuv(a), yuv(b));
+    (a[0] - b[0]).abs() <= SIMILAR_Y
+        && (a[1] - b[1]).abs() <= SIMILAR_U
+        && (a[2] - b[2]).abs() <= SIMILAR_V
+        && (a[3] - b[3]).abs() <= SIMILAR_A
+}
+
+fn distance(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let (a, b) = (yuv(a), yuv(b));
+    48.0 * (a[0] - b[0]).abs()
+        + 7.0 * (a[1] - b[1]).abs()
+        + 6.0 * (a[2] - b[2]).abs()
+        + 48.0 * (a[3] - b[3]).abs()
+}
+
+/// Weighted average of unmultiplied colors, weighting each by its alpha so
+/// transparent neighbours don't darken edges.
+fn mix(parts: &[(Rgba<u8>, f32)]) -> Rgba<u8> {
+    let total: f32 = parts.iter().map(|(_, weight)| weight).sum();
+    let alpha: f32 = parts.iter().map(|(pixel, weight)| pixel[3] as f32 * weight).sum();
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let channel = |c: usize| {
+        let sum: f32 = parts.iter().map(|(p, w)| p[c] as f32 * p[3] as f32 * w).sum();
+        (sum / alpha).round() as u8
+    };
+    Rgba([channel(0), channel(1), channel(2), (alpha / total).round() as u8])
+}
+
+/// AdvMAME2x: each pixel becomes four, copying a neighbour into a corner
+/// where two neighbours meet across it.
+fn scale2x(frame: &RgbaImage) -> RgbaImage {
+    let mut out = RgbaImage::new(frame.width() * 2, frame.height() * 2);
+    for y in 0..frame.height() {
+        for x in 0..frame.width() {
+            let (x, y) = (x as i64, y as i64);
+            let e = at(frame, x, y);
+            let (b, d) = (at(frame, x, y - 1), at(frame, x - 1, y));
+            let (f, h) = (at(frame, x + 1, y), at(frame, x, y + 1));
+
+            let corners = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if b == f { f } else { e },
+                    if d == h { d } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 4]
+            };
+            for (i, pixel) in corners.into_iter().enumerate() {
+                let (out_x, out_y) = (x as u32 * 2 + i as u32 % 2, y as u32 * 2 + i as u32 / 2);
+                out.put_pixel(out_x, out_y, pixel);
+            }
+        }
+    }
+    out
+}
+
+/// AdvMAME3x, the same idea over a 3x3 block.
+fn scale3x(frame: &RgbaImage) -> RgbaImage {
+    let mut out = RgbaImage::new(frame.width() * 3, frame.height() * 3);
+    for y in 0..frame.height() {
+        for x in 0..frame.width() {
+            let (x, y) = (x as i64, y as i64);
+            let n = |dx, dy| at(frame, x + dx, y + dy);
+            let (a, b, c) = (n(-1, -1), n(0, -1), n(1, -1));
+            let (d, e, f) = (n(-1, 0), n(0, 0), n(1, 0));
+            let (g, h, i) = (n(-1, 1), n(0, 1), n(1, 1));
+
+            let block = if b != h && d != f {
+                [
+                    if d == b { d } else { e },
+                    if (d == b && e != c) || (b == f && e != a) { b } else { e },
+                    if b == f { f } else { e },
+                    if (d == b && e != g) || (d == h && e != a) { d } else { e },
+                    e,
+                    if (b == f && e != i) || (h == f && e != c) { f } else { e },
+                    if d == h { d } else { e },
+                    if (d == h && e != i) || (h == f && e != g) { h } else { e },
+                    if h == f { f } else { e },
+                ]
+            } else {
+                [e; 9]
+            };
+            for (index, pixel) in block.into_iter().enumerate() {
+                let index = index as u32;
+                out.put_pixel(x as u32 * 3 + index % 3, y as u32 * 3 + index / 3, pixel);
+            }
+        }
+    }
+    out
+}
+
+/// Neighbours of one source pixel, mirrored so the corner being computed
+/// is always the bottom-right one: `(1, 1)` is the diagonal neighbour
+/// nearest that corner.
+type Sample<'a> = &'a dyn Fn(i64, i64) -> Rgba<u8>;
+
+/// Runs a 2x filter that decides each output pixel from the source pixel's
+/// neighbourhood, one corner at a time.
+fn by_corner(frame: &RgbaImage, corner: fn(Sample) -> Rgba<u8>) -> RgbaImage {
+    let mut out = RgbaImage::new(frame.width() * 2, frame.height() * 2);
+    for y in 0..frame.height() {
+        for x in 0..frame.width() {
+            for (sx, sy) in [(-1, -1), (1, -1), (-1, 1), (1, 1)] {
+                let sample = |dx: i64, dy: i64| at(frame, x as i64 + dx * sx, y as i64 + dy * sy);
+                let (out_x, out_y) = (x * 2 + (sx > 0) as u32, y * 2 + (sy > 0) as u32);
+                out.put_pixel(out_x, out_y, corner(&sample));
+            }
+        }
+    }
+    out
+}
+
+/// Borrows hq2x's similarity test and blend weights, but not its 256-entry
+/// table over all eight neighbours: the center is blended with whichever of
+/// the three pixels touching the corner continue across it. That covers
+/// the cases that matter for outlined sprites, so it is close to hq2x on
+/// Clippy without claiming to be it.
+fn smooth2x_corner(n: Sample) -> Rgba<u8> {
+    let (e, f, h, i) = (n(0, 0), n(1, 0), n(0, 1), n(1, 1));
+
+    if similar(f, h) && !similar(e, f) {
+        // A diagonal edge cuts the corner.
+        if similar(e, i) {
+            mix(&[(e, 6.0), (f, 1.0), (h, 1.0)])
+        } else {
+            mix(&[(e, 2.0), (f, 3.0), (h, 3.0)])
+        }
+    } else if !similar(e, f) && !similar(e, h) {
+        mix(&[(e, 2.0), (f, 1.0), (h, 1.0)])
+    } else if !similar(e, i) && (similar(e, f) || similar(e, h)) && !similar(f, h) {
+        mix(&[(e, 3.0), (i, 1.0)])
+    } else {
+        e
+    }
+}
+
+/// 2xBR level 1 for the bottom-right corner, in the usual naming:
+///
+/// ```text
+///        A1 B1 C1
+///     A0 A  B  C  C4
+///     D0 D  E  F  F4
+///     G0 G  H  I  I4
+///        G5 H5 I5
+/// ```
+fn xbr_corner(n: Sample) -> Rgba<u8> {
+    let (b, c, d, e, f) = (n(0, -1), n(1, -1), n(-1, 0), n(0, 0), n(1, 0));
+    let (g, h, i) = (n(-1, 1), n(0, 1), n(1, 1));
+    let (f4, i4, h5, i5) = (n(2, 0), n(2, 1), n(0, 2), n(1, 2));
+
+    // How much an edge running F-H, versus one running E-I, explains the
+    // surrounding gradients.
+    let along_fh = distance(e, c) + distance(e, g) + distance(i, h5) + distance(i, f4)
+        + 4.0 * distance(h, f);
+    let along_ei = distance(h, d) + distance(h, i5) + distance(f, i4) + distance(f, b)
+        + 4.0 * distance(e, i);
+
+    if along_fh < along_ei && e != f && e != h {
+        let closer = if distance(e, f) <= distance(e, h) { f } else { h };
+        mix(&[(e, 1.0), (closer, 1.0)])
+    } else {
+        e
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot::{self, frame};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const FILTERS: [Upscale; 4] =
+        [Upscale::Scale2x, Upscale::Scale3x, Upscale::Smooth2x, Upscale::Xbr];
+    const INK: Rgba<u8> = Rgba([40, 40, 60, 255]);
+    const PAPER: Rgba<u8> = Rgba([230, 230, 210, 255]);
+    const CLEAR: Rgba<u8> = Rgba([255, 0, 255, 0]);
+
+    /// An outlined diagonal and a staircase on a transparent background,
+    /// with the magenta the sprite sheet hides under its transparency.
+    fn fixture() -> RgbaImage {
+        RgbaImage::from_fn(8, 8, |x, y| match (x, y) {
+            _ if x == y => INK,
+            _ if x == y + 1 || y == x + 1 => PAPER,
+            _ if y == 7 - x / 2 => INK,
+            _ => CLEAR,
+        })
+    }
+
+    /// Random grids over a few colors, so neighbours are often equal.
+    fn grids() -> impl Iterator<Item = RgbaImage> {
+        let colors = [INK, PAPER, Rgba([200, 40, 40, 255])];
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..200).map(move |_| {
+            let (width, height) = (rng.gen_range(1..6), rng.gen_range(1..6));
+            RgbaImage::from_fn(width, height, |_, _| colors[rng.gen_range(0..colors.len())])
+        })
+    }
+
+    /// The 3x3 neighbourhood `[A, B, C, D, E, F, G, H, I]` around a pixel,
+    /// with the edges repeated.
+    fn neighbourhood(frame: &RgbaImage, x: u32, y: u32) -> [Rgba<u8>; 9] {
+        let (x, y) = (x as i64, y as i64);
+        std::array::from_fn(|index| at(frame, x + index as i64 % 3 - 1, y + index as i64 / 3 - 1))
+    }
+
+    #[test]
+    fn scale2x_follows_advmame2x() {
+        for grid in grids() {
+            let scaled = scale2x(&grid);
+            for (x, y, _) in grid.enumerate_pixels() {
+                let [_, b, _, d, e, f, _, h, _] = neighbourhood(&grid, x, y);
+                // As written on the AdvMAME page.
+                let expected = [
+                    if d == b && b != f && d != h { d } else { e },
+                    if b == f && b != d && f != h { f } else { e },
+                    if d == h && d != b && h != f { d } else { e },
+                    if h == f && h != d && f != b { f } else { e },
+                ];
+                for (index, expected) in expected.into_iter().enumerate() {
+                    let (dx, dy) = (index as u32 % 2, index as u32 / 2);
+                    assert_eq!(*scaled.get_pixel(x * 2 + dx, y * 2 + dy), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scale3x_follows_advmame3x() {
+        for grid in grids() {
+            let scaled = scale3x(&grid);
+            for (x, y, _) in grid.enumerate_pixels() {
+                let [a, b, c, d, e, f, g, h, i] = neighbourhood(&grid, x, y);
+                let expected = if b != h && d != f {
+                    [
+                        if d == b { d } else { e },
+                        if (d == b && e != c) || (b == f && e != a) { b } else { e },
+                        if b == f { f } else { e },
+                        if (d == b && e != g) || (d == h && e != a) { d } else { e },
+                        e,
+                        if (b == f && e != i) || (h == f && e != c) { f } else { e },
+                        if d == h { d } else { e },
+                        if (d == h && e != i) || (h == f && e != g) { h } else { e },
+                        if h == f { f } else { e },
+                    ]
+                } else {
+                    [e; 9]
+                };
+                for (index, expected) in expected.into_iter().enumerate() {
+                    let (dx, dy) = (index as u32 % 3, index as u32 / 3);
+                    assert_eq!(*scaled.get_pixel(x * 3 + dx, y * 3 + dy), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn scale2x_rounds_a_diagonal() {
+        let grid = RgbaImage::from_fn(2, 2, |x, y| if x == y { INK } else { PAPER });
+        let scaled = scale2x(&grid);
+        // The paper above the diagonal takes ink in the corner facing it.
+        assert_eq!(*scaled.get_pixel(2, 1), INK);
+        assert_eq!(*scaled.get_pixel(3, 0), PAPER);
+        assert_eq!(*scaled.get_pixel(0, 0), INK);
+    }
+
+    #[test]
+    fn filters_match_snapshots() {
+        for filter in FILTERS {
+            let name = filter.label().to_lowercase();
+            for (input, image) in [("fixture", fixture()), ("frame0", frame(0))] {
+                let scaled = apply(filter, &image);
+                let factor = filter.factor();
+                assert_eq!(scaled.dimensions(), (image.width() * factor, image.height() * factor));
+                snapshot::assert_image(&format!("upscale-{name}-{input}.png"), &scaled);
+            }
+        }
+    }
+
+    #[test]
+    fn to_scale_skips_filters_larger_than_the_scale() {
+        let image = frame(0);
+        assert!(to_scale(Upscale::Scale2x, &image, 1) == image);
+        assert!(to_scale(Upscale::Scale3x, &image, 2) == to_scale(Upscale::Linear, &image, 2));
+        assert!(to_scale(Upscale::Scale2x, &image, 2) == scale2x(&image));
+    }
+
+    #[test]
+    fn smooth_resize_keeps_hidden_colors_hidden() {
+        let mut black = fixture();
+        for pixel in black.pixels_mut().filter(|pixel| pixel[3] == 0) {
+            *pixel = Rgba([0, 0, 0, 0]);
+        }
+        let resized = resize_smooth(&fixture(), 5, 5);
+        assert!(resized == resize_smooth(&black, 5, 5));
+        snapshot::assert_image("upscale-smooth-fixture.png", &resized);
+    }
+}